@@ -0,0 +1,116 @@
+use crate::memory;
+use fixed_size_block::FixedSizeBlockAllocator;
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::structures::paging::{Page, PageTableFlags, Size4KiB};
+use x86_64::VirtAddr;
+
+pub mod bump;
+pub mod fixed_size_block;
+pub mod linked_list;
+
+pub const HEAP_START: usize = 0x_4444_4444_0000;
+pub const HEAP_SIZE: usize = 1024 * 1024; // 1 MiB
+
+// Any of the allocators in this module can be used here:
+// `bump::BumpAllocator`, `linked_list::LinkedListAllocator` or `FixedSizeBlockAllocator`.
+#[global_allocator]
+static ALLOCATOR: Locked<FixedSizeBlockAllocator> = Locked::new(FixedSizeBlockAllocator::new());
+
+/// Maps the heap region and hands it to the global allocator.
+/// Must be called after `memory::init`.
+pub fn init_heap() -> Result<(), MapToError<Size4KiB>> {
+    let page_range = {
+        let heap_start = VirtAddr::new(HEAP_START as u64);
+        let heap_end = heap_start + HEAP_SIZE - 1u64;
+        let heap_start_page: Page = Page::containing_address(heap_start);
+        let heap_end_page: Page = Page::containing_address(heap_end);
+        Page::range_inclusive(heap_start_page, heap_end_page)
+    };
+
+    for page in page_range {
+        memory::map_page(page, PageTableFlags::PRESENT | PageTableFlags::WRITABLE)?;
+    }
+
+    unsafe {
+        ALLOCATOR.lock().init(HEAP_START, HEAP_SIZE);
+    }
+
+    Ok(())
+}
+
+/// `GlobalAlloc` takes `&self`, so the allocators are wrapped in a spinlock
+/// to get mutable access to their state.
+pub struct Locked<A> {
+    inner: spin::Mutex<A>,
+}
+
+impl<A> Locked<A> {
+    pub const fn new(inner: A) -> Self {
+        Locked {
+            inner: spin::Mutex::new(inner),
+        }
+    }
+
+    pub fn lock(&self) -> spin::MutexGuard<'_, A> {
+        self.inner.lock()
+    }
+}
+
+/// Aligns `addr` upwards to `align`, which must be a power of two.
+fn align_up(addr: usize, align: usize) -> usize {
+    (addr + align - 1) & !(align - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::collections::BTreeMap;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use super::HEAP_SIZE;
+
+    #[test_case]
+    fn simple_allocation() {
+        let heap_value_1 = Box::new(41);
+        let heap_value_2 = Box::new(13);
+        assert_eq!(*heap_value_1, 41);
+        assert_eq!(*heap_value_2, 13);
+    }
+
+    #[test_case]
+    fn large_vec() {
+        let n = 1000;
+        let mut vec = Vec::new();
+        for i in 0..n {
+            vec.push(i);
+        }
+        assert_eq!(vec.iter().sum::<u64>(), (n - 1) * n / 2);
+    }
+
+    #[test_case]
+    fn many_boxes_reuse_memory() {
+        for i in 0..HEAP_SIZE {
+            let x = Box::new(i);
+            assert_eq!(*x, i);
+        }
+    }
+
+    #[test_case]
+    fn many_boxes_long_lived() {
+        let long_lived = Box::new(1);
+        for i in 0..HEAP_SIZE {
+            let x = Box::new(i);
+            assert_eq!(*x, i);
+        }
+        assert_eq!(*long_lived, 1);
+    }
+
+    #[test_case]
+    fn string_and_btree_map() {
+        let mut map = BTreeMap::new();
+        map.insert(String::from("root"), 0);
+        map.insert(String::from("docs"), 1);
+        assert_eq!(map.get("docs"), Some(&1));
+        assert_eq!(map.keys().next().map(String::as_str), Some("docs"));
+    }
+}
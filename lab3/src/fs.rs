@@ -0,0 +1,81 @@
+use core::fmt;
+
+pub mod path;
+pub mod ramfs;
+
+/// Index of an inode inside a filesystem.
+pub type InodeId = usize;
+
+pub const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsError {
+    NotFound,
+    AlreadyExists,
+    NotADirectory,
+    IsADirectory,
+    DirectoryNotEmpty,
+    InvalidName,
+    NameTooLong,
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            FsError::NotFound => "No such file or directory",
+            FsError::AlreadyExists => "File or directory already exists",
+            FsError::NotADirectory => "Not a directory",
+            FsError::IsADirectory => "Is a directory",
+            FsError::DirectoryNotEmpty => "Directory is not empty",
+            FsError::InvalidName => "Invalid name",
+            FsError::NameTooLong => "The maximum length of a name is 64 characters",
+        };
+        f.write_str(message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InodeKind {
+    Directory,
+    File,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub kind: InodeKind,
+    /// Content length in bytes for files, number of entries for directories.
+    pub size: usize,
+}
+
+/// Operations the shell needs from a filesystem.
+/// Directory entries are addressed by the parent directory and the entry name.
+pub trait Vfs {
+    fn root(&self) -> InodeId;
+    fn parent(&self, inode: InodeId) -> Result<InodeId, FsError>;
+    fn name(&self, inode: InodeId) -> Result<&str, FsError>;
+    fn metadata(&self, inode: InodeId) -> Result<Metadata, FsError>;
+    fn children(&self, dir: InodeId) -> Result<&[InodeId], FsError>;
+    fn lookup(&self, dir: InodeId, name: &str) -> Result<InodeId, FsError>;
+
+    fn create_dir(&mut self, parent: InodeId, name: &str) -> Result<InodeId, FsError>;
+    /// Fails with `DirectoryNotEmpty` unless the directory has no entries.
+    fn remove_dir(&mut self, parent: InodeId, name: &str) -> Result<(), FsError>;
+
+    fn create_file(&mut self, parent: InodeId, name: &str) -> Result<InodeId, FsError>;
+    fn remove_file(&mut self, parent: InodeId, name: &str) -> Result<(), FsError>;
+    fn read(&self, file: InodeId) -> Result<&[u8], FsError>;
+    /// Replaces the file content.
+    fn write(&mut self, file: InodeId, data: &[u8]) -> Result<(), FsError>;
+    fn append(&mut self, file: InodeId, data: &[u8]) -> Result<(), FsError>;
+}
+
+/// Checks that `name` can be used as a single directory entry.
+pub fn validate_name(name: &str) -> Result<(), FsError> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(FsError::InvalidName);
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(FsError::NameTooLong);
+    }
+    Ok(())
+}
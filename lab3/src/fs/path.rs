@@ -0,0 +1,95 @@
+use super::{FsError, InodeId, Vfs};
+
+/// Resolves `path` to an inode. Relative paths start at `cwd`, paths starting with `/` at the root.
+/// Empty components (repeated or trailing slashes) and `.` are skipped, `..` at the root stays at the root.
+pub fn resolve<V: Vfs + ?Sized>(fs: &V, cwd: InodeId, path: &str) -> Result<InodeId, FsError> {
+    let mut current = if path.starts_with('/') { fs.root() } else { cwd };
+
+    for component in path.split('/') {
+        current = match component {
+            "" | "." => current,
+            ".." => fs.parent(current)?,
+            name => fs.lookup(current, name)?,
+        };
+    }
+
+    Ok(current)
+}
+
+/// Resolves everything but the last component of `path` and returns the directory together with
+/// the last component, which is what creating or removing an entry needs.
+pub fn resolve_parent<'a, V: Vfs + ?Sized>(fs: &V, cwd: InodeId, path: &'a str) -> Result<(InodeId, &'a str), FsError> {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        // the root (or an empty path) has no parent entry
+        return Err(FsError::InvalidName);
+    }
+
+    let (parent_path, name) = match trimmed.rfind('/') {
+        Some(index) => (&trimmed[..index + 1], &trimmed[index + 1..]),
+        None => ("", trimmed),
+    };
+
+    let parent = resolve(fs, cwd, parent_path)?;
+    // make sure intermediate components are directories
+    fs.children(parent)?;
+    Ok((parent, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::ramfs::RamFs;
+
+    /// Builds `/a/b/c` and a file `/a/notes`.
+    fn sample_fs() -> (RamFs, InodeId, InodeId, InodeId) {
+        let mut fs = RamFs::new();
+        let a = fs.create_dir(fs.root(), "a").unwrap();
+        let b = fs.create_dir(a, "b").unwrap();
+        let c = fs.create_dir(b, "c").unwrap();
+        fs.create_file(a, "notes").unwrap();
+        (fs, a, b, c)
+    }
+
+    #[test_case]
+    fn resolve_absolute_and_relative_paths() {
+        let (fs, a, b, c) = sample_fs();
+
+        assert_eq!(resolve(&fs, c, "/"), Ok(fs.root()));
+        assert_eq!(resolve(&fs, c, "/a/b"), Ok(b));
+        assert_eq!(resolve(&fs, a, "b/c"), Ok(c));
+        assert_eq!(resolve(&fs, a, ""), Ok(a));
+    }
+
+    #[test_case]
+    fn resolve_dots_and_repeated_slashes() {
+        let (fs, a, b, c) = sample_fs();
+
+        assert_eq!(resolve(&fs, c, ".."), Ok(b));
+        assert_eq!(resolve(&fs, c, "../../"), Ok(a));
+        assert_eq!(resolve(&fs, a, "./b//c/."), Ok(c));
+        assert_eq!(resolve(&fs, a, "b/../b/c"), Ok(c));
+        assert_eq!(resolve(&fs, a, "/../.."), Ok(fs.root()));
+    }
+
+    #[test_case]
+    fn resolve_errors() {
+        let (fs, a, _, _) = sample_fs();
+
+        assert_eq!(resolve(&fs, a, "missing"), Err(FsError::NotFound));
+        assert_eq!(resolve(&fs, a, "notes/x"), Err(FsError::NotADirectory));
+    }
+
+    #[test_case]
+    fn resolve_parent_splits_last_component() {
+        let (fs, a, b, c) = sample_fs();
+
+        assert_eq!(resolve_parent(&fs, a, "x"), Ok((a, "x")));
+        assert_eq!(resolve_parent(&fs, c, "../x/"), Ok((b, "x")));
+        assert_eq!(resolve_parent(&fs, c, "/a/b/c"), Ok((b, "c")));
+        assert_eq!(resolve_parent(&fs, c, "/x"), Ok((fs.root(), "x")));
+        assert_eq!(resolve_parent(&fs, c, "/"), Err(FsError::InvalidName));
+        assert_eq!(resolve_parent(&fs, a, "notes/x"), Err(FsError::NotADirectory));
+        assert_eq!(resolve_parent(&fs, a, "missing/x"), Err(FsError::NotFound));
+    }
+}
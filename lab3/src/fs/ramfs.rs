@@ -0,0 +1,282 @@
+use super::{validate_name, FsError, InodeId, InodeKind, Metadata, Vfs};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const ROOT_INODE: InodeId = 0;
+
+enum InodeData {
+    Directory(Vec<InodeId>),
+    File(Vec<u8>),
+}
+
+struct Inode {
+    name: String,
+    parent: InodeId,
+    data: InodeData,
+}
+
+/// In-memory filesystem. Inodes live in a table indexed by `InodeId`,
+/// the slots of removed inodes are reused by the next created ones.
+pub struct RamFs {
+    inodes: Vec<Option<Inode>>,
+    free_slots: Vec<InodeId>,
+}
+
+impl RamFs {
+    pub fn new() -> RamFs {
+        let root = Inode {
+            name: String::new(),
+            parent: ROOT_INODE,
+            data: InodeData::Directory(Vec::new()),
+        };
+        RamFs {
+            inodes: alloc::vec![Some(root)],
+            free_slots: Vec::new(),
+        }
+    }
+
+    fn inode(&self, inode: InodeId) -> Result<&Inode, FsError> {
+        match self.inodes.get(inode) {
+            Some(Some(inode)) => Ok(inode),
+            _ => Err(FsError::NotFound),
+        }
+    }
+
+    fn inode_mut(&mut self, inode: InodeId) -> Result<&mut Inode, FsError> {
+        match self.inodes.get_mut(inode) {
+            Some(Some(inode)) => Ok(inode),
+            _ => Err(FsError::NotFound),
+        }
+    }
+
+    fn entries_mut(&mut self, dir: InodeId) -> Result<&mut Vec<InodeId>, FsError> {
+        match &mut self.inode_mut(dir)?.data {
+            InodeData::Directory(entries) => Ok(entries),
+            InodeData::File(_) => Err(FsError::NotADirectory),
+        }
+    }
+
+    fn content_mut(&mut self, file: InodeId) -> Result<&mut Vec<u8>, FsError> {
+        match &mut self.inode_mut(file)?.data {
+            InodeData::File(content) => Ok(content),
+            InodeData::Directory(_) => Err(FsError::IsADirectory),
+        }
+    }
+
+    fn create(&mut self, parent: InodeId, name: &str, data: InodeData) -> Result<InodeId, FsError> {
+        validate_name(name)?;
+        match self.lookup(parent, name) {
+            Ok(_) => return Err(FsError::AlreadyExists),
+            Err(FsError::NotFound) => {}
+            Err(err) => return Err(err),
+        }
+
+        let inode = Inode {
+            name: String::from(name),
+            parent,
+            data,
+        };
+        let id = match self.free_slots.pop() {
+            Some(id) => {
+                self.inodes[id] = Some(inode);
+                id
+            }
+            None => {
+                self.inodes.push(Some(inode));
+                self.inodes.len() - 1
+            }
+        };
+
+        self.entries_mut(parent)?.push(id);
+        Ok(id)
+    }
+
+    fn remove(&mut self, parent: InodeId, name: &str, kind: InodeKind) -> Result<(), FsError> {
+        let id = self.lookup(parent, name)?;
+        match (&self.inode(id)?.data, kind) {
+            (InodeData::Directory(entries), InodeKind::Directory) if !entries.is_empty() => {
+                return Err(FsError::DirectoryNotEmpty)
+            }
+            (InodeData::Directory(_), InodeKind::File) => return Err(FsError::IsADirectory),
+            (InodeData::File(_), InodeKind::Directory) => return Err(FsError::NotADirectory),
+            _ => {}
+        }
+
+        self.entries_mut(parent)?.retain(|&entry| entry != id);
+        self.inodes[id] = None;
+        self.free_slots.push(id);
+        Ok(())
+    }
+}
+
+impl Vfs for RamFs {
+    fn root(&self) -> InodeId {
+        ROOT_INODE
+    }
+
+    fn parent(&self, inode: InodeId) -> Result<InodeId, FsError> {
+        Ok(self.inode(inode)?.parent)
+    }
+
+    fn name(&self, inode: InodeId) -> Result<&str, FsError> {
+        Ok(&self.inode(inode)?.name)
+    }
+
+    fn metadata(&self, inode: InodeId) -> Result<Metadata, FsError> {
+        let metadata = match &self.inode(inode)?.data {
+            InodeData::Directory(entries) => Metadata {
+                kind: InodeKind::Directory,
+                size: entries.len(),
+            },
+            InodeData::File(content) => Metadata {
+                kind: InodeKind::File,
+                size: content.len(),
+            },
+        };
+        Ok(metadata)
+    }
+
+    fn children(&self, dir: InodeId) -> Result<&[InodeId], FsError> {
+        match &self.inode(dir)?.data {
+            InodeData::Directory(entries) => Ok(entries),
+            InodeData::File(_) => Err(FsError::NotADirectory),
+        }
+    }
+
+    fn lookup(&self, dir: InodeId, name: &str) -> Result<InodeId, FsError> {
+        self.children(dir)?
+            .iter()
+            .copied()
+            .find(|&entry| self.name(entry) == Ok(name))
+            .ok_or(FsError::NotFound)
+    }
+
+    fn create_dir(&mut self, parent: InodeId, name: &str) -> Result<InodeId, FsError> {
+        self.create(parent, name, InodeData::Directory(Vec::new()))
+    }
+
+    fn remove_dir(&mut self, parent: InodeId, name: &str) -> Result<(), FsError> {
+        self.remove(parent, name, InodeKind::Directory)
+    }
+
+    fn create_file(&mut self, parent: InodeId, name: &str) -> Result<InodeId, FsError> {
+        self.create(parent, name, InodeData::File(Vec::new()))
+    }
+
+    fn remove_file(&mut self, parent: InodeId, name: &str) -> Result<(), FsError> {
+        self.remove(parent, name, InodeKind::File)
+    }
+
+    fn read(&self, file: InodeId) -> Result<&[u8], FsError> {
+        match &self.inode(file)?.data {
+            InodeData::File(content) => Ok(content),
+            InodeData::Directory(_) => Err(FsError::IsADirectory),
+        }
+    }
+
+    fn write(&mut self, file: InodeId, data: &[u8]) -> Result<(), FsError> {
+        let content = self.content_mut(file)?;
+        content.clear();
+        content.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn append(&mut self, file: InodeId, data: &[u8]) -> Result<(), FsError> {
+        self.content_mut(file)?.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::MAX_NAME_LENGTH;
+
+    #[test_case]
+    fn create_dir_and_lookup() {
+        let mut fs = RamFs::new();
+        let docs = fs.create_dir(fs.root(), "docs").unwrap();
+
+        assert_eq!(fs.lookup(fs.root(), "docs"), Ok(docs));
+        assert_eq!(fs.parent(docs), Ok(fs.root()));
+        assert_eq!(fs.name(docs), Ok("docs"));
+        assert_eq!(fs.children(fs.root()), Ok(&[docs][..]));
+    }
+
+    #[test_case]
+    fn create_rejects_duplicates_and_bad_names() {
+        let mut fs = RamFs::new();
+        fs.create_dir(fs.root(), "docs").unwrap();
+
+        assert_eq!(fs.create_dir(fs.root(), "docs"), Err(FsError::AlreadyExists));
+        assert_eq!(fs.create_file(fs.root(), "docs"), Err(FsError::AlreadyExists));
+        assert_eq!(fs.create_dir(fs.root(), ""), Err(FsError::InvalidName));
+        assert_eq!(fs.create_dir(fs.root(), ".."), Err(FsError::InvalidName));
+        assert_eq!(fs.create_dir(fs.root(), "a/b"), Err(FsError::InvalidName));
+
+        let long_name = "x".repeat(MAX_NAME_LENGTH + 1);
+        assert_eq!(fs.create_dir(fs.root(), &long_name), Err(FsError::NameTooLong));
+        assert!(fs.create_dir(fs.root(), &long_name[1..]).is_ok());
+    }
+
+    #[test_case]
+    fn remove_dir_requires_empty_directory() {
+        let mut fs = RamFs::new();
+        let docs = fs.create_dir(fs.root(), "docs").unwrap();
+        fs.create_dir(docs, "notes").unwrap();
+
+        assert_eq!(fs.remove_dir(fs.root(), "docs"), Err(FsError::DirectoryNotEmpty));
+        fs.remove_dir(docs, "notes").unwrap();
+        fs.remove_dir(fs.root(), "docs").unwrap();
+        assert_eq!(fs.lookup(fs.root(), "docs"), Err(FsError::NotFound));
+        assert_eq!(fs.children(fs.root()), Ok(&[][..]));
+    }
+
+    #[test_case]
+    fn removed_slots_are_reused() {
+        let mut fs = RamFs::new();
+        let first = fs.create_dir(fs.root(), "first").unwrap();
+        fs.remove_dir(fs.root(), "first").unwrap();
+        let second = fs.create_dir(fs.root(), "second").unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(fs.inodes.len(), 2);
+        assert_eq!(fs.name(second), Ok("second"));
+    }
+
+    #[test_case]
+    fn many_directories() {
+        let mut fs = RamFs::new();
+        for i in 0..500 {
+            fs.create_dir(fs.root(), &alloc::format!("dir{}", i)).unwrap();
+        }
+        assert_eq!(fs.metadata(fs.root()).unwrap().size, 500);
+    }
+
+    #[test_case]
+    fn file_write_append_read() {
+        let mut fs = RamFs::new();
+        let file = fs.create_file(fs.root(), "notes").unwrap();
+
+        fs.write(file, b"hello").unwrap();
+        fs.append(file, b" world").unwrap();
+        assert_eq!(fs.read(file), Ok(&b"hello world"[..]));
+        assert_eq!(fs.metadata(file), Ok(Metadata { kind: InodeKind::File, size: 11 }));
+
+        fs.write(file, b"bye").unwrap();
+        assert_eq!(fs.read(file), Ok(&b"bye"[..]));
+    }
+
+    #[test_case]
+    fn kind_mismatches_are_errors() {
+        let mut fs = RamFs::new();
+        let file = fs.create_file(fs.root(), "notes").unwrap();
+        fs.create_dir(fs.root(), "docs").unwrap();
+
+        assert_eq!(fs.create_dir(file, "x"), Err(FsError::NotADirectory));
+        assert_eq!(fs.read(fs.root()), Err(FsError::IsADirectory));
+        assert_eq!(fs.remove_dir(fs.root(), "notes"), Err(FsError::NotADirectory));
+        assert_eq!(fs.remove_file(fs.root(), "docs"), Err(FsError::IsADirectory));
+        fs.remove_file(fs.root(), "notes").unwrap();
+    }
+}
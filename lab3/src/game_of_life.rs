@@ -1,9 +1,38 @@
 use crate::vga_buf::*;
+use lazy_static::lazy_static;
+use pc_keyboard::DecodedKey;
+use spin::Mutex;
+use crate::print;
+use crate::shell::ERROR;
+use crate::thread;
+use crate::time;
+use x86_64::instructions::interrupts::without_interrupts;
 
-pub const HEIGHT: usize = 25;
+// the last screen row is the status bar
+pub const HEIGHT: usize = TEXT_HEIGHT as usize;
 pub const WIDTH: usize = 80;
+const GENERATION_MS: u64 = 100;
 
-const MAP: [&str; 25] = [
+struct GameOfLife {
+    running: bool,
+    // the console the game was started on, it keeps running there while another one is shown
+    console: usize,
+    thread_alive: bool,
+    current_gen: [[u8; WIDTH]; HEIGHT],
+}
+
+lazy_static! {
+    static ref GAME: Mutex<GameOfLife> = Mutex::new(
+        GameOfLife {
+            running: false,
+            console: 0,
+            thread_alive: false,
+            current_gen: [[b' '; WIDTH]; HEIGHT],
+        }
+    );
+}
+
+const MAP: [&str; HEIGHT] = [
     "                                                                                ",
     "                                                                                ",
     "                                                                                ",
@@ -27,30 +56,21 @@ const MAP: [&str; 25] = [
     "                                                                                ",
     "                                                                                ",
     "                                                                                ",
-    "                                                                                ",
     "                                                                                "
 ];
 
-pub fn sleep()
+/// Draws the field into the text area, cut to its size, the status bar and other regions stay.
+pub fn render(game_field: &[[u8; WIDTH]; HEIGHT], screen: &mut Screen)
 {
-    let mut number = 2.24632767;
-    for i in 0..50000{
-        number *= number;
-    }
-}
-
-pub fn render(game_field: &[[u8; 80]; 25], vga_driver: &mut VGADriver)
-{
-    for i in 0..game_field.len()
+    let mut field = screen.text_area();
+    field.set_color(screen.color());
+    for (i, row) in game_field.iter().enumerate()
     {
-        for j in 0..game_field[0].len()
-        {
-            vga_driver.write_byte_char((i * 80 + j) as u32, game_field[i][j]);
-        }
+        field.write_line(screen, i as u32, row);
     }
 }
 
-pub fn get_count_nearest_cells(game_field: &[[u8; 80]; 25], i: usize, j: usize) -> u32
+pub fn get_count_nearest_cells(game_field: &[[u8; WIDTH]; HEIGHT], i: usize, j: usize) -> u32
 {
     let mut count: u32 = 0;
 
@@ -89,43 +109,115 @@ pub fn get_count_nearest_cells(game_field: &[[u8; 80]; 25], i: usize, j: usize)
     return count;
 }
 
-pub fn game_of_life(vga_driver: &mut VGADriver)
+pub fn next_generation(current_gen: &[[u8; WIDTH]; HEIGHT]) -> [[u8; WIDTH]; HEIGHT]
+{
+    let mut evolution: [[u8; WIDTH]; HEIGHT] = [[b' '; WIDTH]; HEIGHT];
+
+    for i in 0..current_gen.len()
+    {
+        for j in 0..current_gen[0].len()
+        {
+            let count_nearest_cells: u32 = get_count_nearest_cells(current_gen, i, j);
+
+            if current_gen[i][j] == b'x' && (count_nearest_cells == 3 || count_nearest_cells == 2)
+            {
+                evolution[i][j] = b'x';
+            } else if current_gen[i][j] == b' ' && count_nearest_cells == 3
+            {
+                evolution[i][j] = b'x';
+            } else {
+                evolution[i][j] = b' ';
+            }
+        }
+    }
+    return evolution;
+}
+
+// GAME and CONSOLES are shared with the game thread, they are locked with interrupts disabled
+// so a thread is never preempted while holding them
+
+pub fn is_running_on(console: usize) -> bool
 {
-    let mut current_gen: [[u8; 80]; 25] = [[0; 80]; 25];
+    without_interrupts(|| {
+        let game = GAME.lock();
+        game.running && game.console == console
+    })
+}
+
+pub fn is_exit_key(key: DecodedKey) -> bool
+{
+    match key {
+        DecodedKey::Unicode(c) => c == 'q' || c == '\u{1b}',
+        DecodedKey::RawKey(_) => false,
+    }
+}
+
+/// Loads the initial map on `console` and starts a thread evolving it every `GENERATION_MS` milliseconds.
+pub fn start(console: usize)
+{
+    let spawn_thread = match without_interrupts(|| start_locked(console))
+    {
+        Ok(spawn_thread) => spawn_thread,
+        Err(other) => return print!("\n{} The game is already running on tty{}", ERROR, other + 1),
+    };
+    if spawn_thread && thread::spawn("life", run).is_none()
+    {
+        without_interrupts(|| GAME.lock().thread_alive = false);
+        stop();
+        print!("\n{} Too many threads", ERROR);
+    }
+}
+
+/// Returns whether a new thread is needed, the previous one may not have noticed the last `stop` yet.
+/// Fails with the console of a game that is still running.
+fn start_locked(console: usize) -> Result<bool, usize>
+{
+    let mut game = GAME.lock();
+    if game.running
+    {
+        return Err(game.console);
+    }
     for i in 0..MAP.len()
     {
         for (j, byte) in MAP[i].bytes().enumerate()
         {
-            current_gen[i][j] = byte;
+            game.current_gen[i][j] = byte;
         }
     }
-    render(&current_gen, vga_driver);
+    game.running = true;
+    game.console = console;
+    render(&game.current_gen, &mut CONSOLES[console].lock());
+    Ok(!core::mem::replace(&mut game.thread_alive, true))
+}
 
-    // TODO: implement game of life
-    let mut count: u32 = 0;
-    loop {
-        sleep();
-        let mut evolution: [[u8; 80]; 25] = [[0; 80]; 25];
+pub fn stop()
+{
+    without_interrupts(|| {
+        let mut game = GAME.lock();
+        game.running = false;
+        CONSOLES[game.console].lock().clear();
+    });
+}
 
-        for i in 0..current_gen.len()
-        {
-            for j in 0..current_gen[0].len()
+fn run()
+{
+    loop
+    {
+        time::sleep_ms(GENERATION_MS);
+        let running = without_interrupts(|| {
+            let mut game = GAME.lock();
+            if !game.running
             {
-                let count_nearest_cells: u32 = get_count_nearest_cells(&current_gen, i, j);
-
-                if current_gen[i][j] == b'x' && (count_nearest_cells == 3 || count_nearest_cells == 2)
-                {
-                    evolution[i][j] = b'x';
-                } else if current_gen[i][j] == b' ' && count_nearest_cells == 3
-                {
-                    evolution[i][j] = b'x';
-                } else {
-                    evolution[i][j] = b' ';
-                }
+                game.thread_alive = false;
+                return false;
             }
+            game.current_gen = next_generation(&game.current_gen);
+            render(&game.current_gen, &mut CONSOLES[game.console].lock());
+            true
+        });
+        if !running
+        {
+            return;
         }
-        //evolution[0][0] = b'u';
-        current_gen = evolution;
-        render(&current_gen, vga_driver);
     }
-}
\ No newline at end of file
+}
@@ -1,102 +1,214 @@
 use lazy_static::lazy_static;
-use x86_64::structures::idt::{InterruptStackFrame, InterruptDescriptorTable};
+use x86_64::structures::idt::{InterruptStackFrame, InterruptDescriptorTable, PageFaultErrorCode};
+use x86_64::registers::control::Cr2;
 use pic8259::ChainedPics;
-use x86_64::instructions::port::Port;
-use pc_keyboard::{layouts, DecodedKey, HandleControl, Keyboard, ScancodeSet1};
+use x86_64::instructions::{interrupts, port::Port};
 use spin::Mutex;
+use crate::serial::SERIAL1;
+use crate::gdt;
+use crate::task::keyboard;
+use crate::thread;
+use crate::time;
+use crate::status_bar;
+use crate::println;
 
 const PIC_1_OFFSET: u8 = 32;
 const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
 const TIMER_INTERRUPT: u8 = PIC_1_OFFSET;
 const KEYBOARD_INTERRUPT: u8 = PIC_1_OFFSET + 1;
+const SERIAL_INTERRUPT: u8 = PIC_1_OFFSET + 4;
 
 lazy_static! {
     static ref IDT: InterruptDescriptorTable = {
         let mut idt = InterruptDescriptorTable::new();
-        idt.double_fault.set_handler_fn(double_fault_handler);
+        idt.divide_error.set_handler_fn(divide_error_handler);
+        idt.debug.set_handler_fn(debug_handler);
+        idt.non_maskable_interrupt.set_handler_fn(non_maskable_interrupt_handler);
+        idt.breakpoint.set_handler_fn(breakpoint_handler);
+        idt.overflow.set_handler_fn(overflow_handler);
+        idt.bound_range_exceeded.set_handler_fn(bound_range_exceeded_handler);
+        idt.invalid_opcode.set_handler_fn(invalid_opcode_handler);
+        idt.device_not_available.set_handler_fn(device_not_available_handler);
+        unsafe {
+            idt.double_fault
+                .set_handler_fn(double_fault_handler)
+                .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+        idt.invalid_tss.set_handler_fn(invalid_tss_handler);
+        idt.segment_not_present.set_handler_fn(segment_not_present_handler);
+        idt.stack_segment_fault.set_handler_fn(stack_segment_fault_handler);
+        idt.general_protection_fault.set_handler_fn(general_protection_fault_handler);
+        idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.x87_floating_point.set_handler_fn(x87_floating_point_handler);
+        idt.alignment_check.set_handler_fn(alignment_check_handler);
+        idt.machine_check.set_handler_fn(machine_check_handler);
+        idt.simd_floating_point.set_handler_fn(simd_floating_point_handler);
+        idt.virtualization.set_handler_fn(virtualization_handler);
+        idt.vmm_communication_exception.set_handler_fn(vmm_communication_exception_handler);
+        idt.security_exception.set_handler_fn(security_exception_handler);
         idt[TIMER_INTERRUPT as usize].set_handler_fn(timer_interrupt_handler);
         idt[KEYBOARD_INTERRUPT as usize].set_handler_fn(keyboard_interrupt_handler);
+        idt[SERIAL_INTERRUPT as usize].set_handler_fn(serial_interrupt_handler);
         idt
     };
 }
 
-lazy_static! {
-    static ref KEYBOARD: Mutex<Keyboard<layouts::Us104Key, ScancodeSet1>> = Mutex::new(
-        Keyboard::new(
-            layouts::Us104Key, 
-            ScancodeSet1,
-            HandleControl::Ignore
-        )
-    );
-}
-
-lazy_static! {
-    static ref CUSTOM_HANDLERS: Mutex<CustomHandlers> = Mutex::new(
-        {
-            let mut ch = CustomHandlers{
-                timer_interrupt_handler: || {},
-                keyboard_interrupt_handler: |dk| {}
-            };
-            ch
-        }
-    );
-}
-
 static PICS: Mutex<ChainedPics> = spin::Mutex::new(
     unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) }
 );
 
 pub fn init() {
+    gdt::init();
     IDT.load();
     unsafe { PICS.lock().initialize() }
-    x86_64::instructions::interrupts::enable();
+    time::init();
+    // touch the lazy static so the UART is configured before its IRQ is unmasked
+    SERIAL1.lock();
+    unmask_irq(SERIAL_INTERRUPT - PIC_1_OFFSET);
+    interrupts::enable();
 }
 
-pub fn set_keyboard_interrupt_handler(handler: fn(DecodedKey)) {
-    CUSTOM_HANDLERS.lock().keyboard_interrupt_handler  = handler;
+fn unmask_irq(irq: u8) {
+    let mut mask_port: Port<u8> = Port::new(0x21);
+    unsafe {
+        let mask = mask_port.read();
+        mask_port.write(mask & !(1 << irq));
+    }
 }
 
-pub fn set_timer_interrupt_handler(handler: fn()) {
-    CUSTOM_HANDLERS.lock().timer_interrupt_handler  = handler;
+extern "x86-interrupt" fn divide_error_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: DIVIDE ERROR\n{:#?}", stack_frame);
 }
 
+extern "x86-interrupt" fn debug_handler(stack_frame: InterruptStackFrame) {
+    println!("EXCEPTION: DEBUG\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn non_maskable_interrupt_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: NON MASKABLE INTERRUPT\n{:#?}", stack_frame);
+}
 
-struct CustomHandlers {
-    timer_interrupt_handler: fn(),
-    keyboard_interrupt_handler: fn(DecodedKey),
+extern "x86-interrupt" fn breakpoint_handler(stack_frame: InterruptStackFrame) {
+    // a breakpoint is a trap, execution continues after the int3 instruction
+    println!("EXCEPTION: BREAKPOINT\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn overflow_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: OVERFLOW\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn bound_range_exceeded_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: BOUND RANGE EXCEEDED\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn invalid_opcode_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: INVALID OPCODE\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn device_not_available_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: DEVICE NOT AVAILABLE\n{:#?}", stack_frame);
 }
 
 extern "x86-interrupt" fn double_fault_handler(stack_frame: InterruptStackFrame, _error_code: u64) -> ! {
     panic!("DOUBLE FAULT\n{:#?}", stack_frame);
 }
 
+extern "x86-interrupt" fn invalid_tss_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    panic!("EXCEPTION: INVALID TSS (error code {:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn segment_not_present_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    panic!("EXCEPTION: SEGMENT NOT PRESENT (error code {:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn stack_segment_fault_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    panic!("EXCEPTION: STACK SEGMENT FAULT (error code {:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn general_protection_fault_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    panic!("EXCEPTION: GENERAL PROTECTION FAULT (error code {:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn page_fault_handler(stack_frame: InterruptStackFrame, error_code: PageFaultErrorCode) {
+    // CR2 holds the virtual address whose access caused the fault
+    panic!(
+        "EXCEPTION: PAGE FAULT\nAccessed address: {:?}\nError code: {:?}\n{:#?}",
+        Cr2::read(),
+        error_code,
+        stack_frame
+    );
+}
+
+extern "x86-interrupt" fn x87_floating_point_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: x87 FLOATING POINT\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn alignment_check_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    panic!("EXCEPTION: ALIGNMENT CHECK (error code {:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn machine_check_handler(stack_frame: InterruptStackFrame) -> ! {
+    panic!("EXCEPTION: MACHINE CHECK\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn simd_floating_point_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: SIMD FLOATING POINT\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn virtualization_handler(stack_frame: InterruptStackFrame) {
+    panic!("EXCEPTION: VIRTUALIZATION\n{:#?}", stack_frame);
+}
+
+extern "x86-interrupt" fn vmm_communication_exception_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    panic!("EXCEPTION: VMM COMMUNICATION (error code {:#x})\n{:#?}", error_code, stack_frame);
+}
+
+extern "x86-interrupt" fn security_exception_handler(stack_frame: InterruptStackFrame, error_code: u64) {
+    panic!("EXCEPTION: SECURITY EXCEPTION (error code {:#x})\n{:#?}", error_code, stack_frame);
+}
+
 extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    // delegate call to custom handlers function
-    (CUSTOM_HANDLERS.lock().timer_interrupt_handler)();
+    time::on_timer_tick();
+    status_bar::on_timer_tick();
     unsafe {
         PICS.lock().notify_end_of_interrupt(TIMER_INTERRUPT);
     }
+    // may switch to another thread, so the end of interrupt has to be sent before
+    thread::on_timer_tick();
 }
 
 extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    let mut keyboard = KEYBOARD.lock();
     let mut port = Port::new(0x60);
-    
+    let scancode: u8 = unsafe { port.read() };
 
-    let scancode: u8 = unsafe { 
-        port.read()
-
-    };
+    // only queue the scancode and wake the task reading keys, it is decoded there
+    keyboard::add_scancode(scancode);
 
+    unsafe {
+        PICS.lock().notify_end_of_interrupt(KEYBOARD_INTERRUPT);
+    }
+}
 
-    if let Ok(Some(key_event)) = keyboard.add_byte(scancode) {
-        if let Some(key) = keyboard.process_keyevent(key_event) {
-            // delegate call to custom handlers function
-            (CUSTOM_HANDLERS.lock().keyboard_interrupt_handler)(key);
+extern "x86-interrupt" fn serial_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    // drain the UART, the bytes are handled by the shell as if they came from the keyboard
+    loop {
+        // the lock is released before the byte is handled
+        let byte = SERIAL1.lock().try_receive();
+        match byte {
+            Some(byte) => keyboard::add_serial_byte(byte),
+            None => break,
         }
     }
 
     unsafe {
-        PICS.lock().notify_end_of_interrupt(KEYBOARD_INTERRUPT);
+        PICS.lock().notify_end_of_interrupt(SERIAL_INTERRUPT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test_case]
+    fn breakpoint_exception_returns() {
+        x86_64::instructions::interrupts::int3();
     }
 }
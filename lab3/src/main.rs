@@ -1,19 +1,36 @@
 #![no_std] // don't link the Rust standard library
 #![no_main] // disable all Rust-level entry points
 #![feature(abi_x86_interrupt)]
+#![feature(custom_test_frameworks)]
+#![test_runner(crate::testing::test_runner)]
+#![reexport_test_harness_main = "test_main"]
 
+extern crate alloc;
 
-use core::fmt::Write;
 use core::panic::PanicInfo;
-use core::ptr::write;
-use pc_keyboard::DecodedKey;
-use crate::vga_buf::SCREEN;
+use bootloader::{entry_point, BootInfo};
+use crate::task::{executor::Executor, Task};
 
 mod vga_buf;
+mod ring_buffer;
+mod serial;
 mod interrupts;
+mod gdt;
+mod memory;
+mod allocator;
 mod shell;
+mod fs;
+mod game_of_life;
+mod task;
+mod thread;
+mod time;
+mod rtc;
+mod status_bar;
+#[cfg(test)]
+mod testing;
 
 /// This function is called on panic.
+#[cfg(not(test))]
 #[panic_handler]
 fn panic(_info: &PanicInfo) -> ! {
     println!("----------------------------------------------");
@@ -22,19 +39,27 @@ fn panic(_info: &PanicInfo) -> ! {
     loop {}
 }
 
-fn my_keyboard_handler(key: DecodedKey) {
-    shell::handle_keyboard_interrupt(key);
+#[cfg(test)]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    testing::test_panic_handler(info)
 }
 
-fn my_timer_handler() {
-}
+entry_point!(kernel_main);
 
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
+fn kernel_main(boot_info: &'static BootInfo) -> ! {
+    memory::init(boot_info);
+    allocator::init_heap().expect("heap initialization failed");
+    vga_buf::init_scrollback();
+    thread::init();
     shell::init_shell();
-    interrupts::set_keyboard_interrupt_handler(my_keyboard_handler);
-    interrupts::set_timer_interrupt_handler(my_timer_handler);
     interrupts::init();
 
-    loop {}
+    #[cfg(test)]
+    test_main();
+
+    // keys are only queued by the interrupt handlers, the shell reads them as a task with interrupts enabled
+    let mut executor = Executor::new();
+    executor.spawn(Task::new(shell::run()));
+    executor.run();
 }
@@ -0,0 +1,131 @@
+use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
+use bootloader::BootInfo;
+use spin::Mutex;
+use x86_64::registers::control::Cr3;
+use x86_64::structures::paging::mapper::MapToError;
+use x86_64::structures::paging::{
+    FrameAllocator, Mapper, OffsetPageTable, Page, PageTable, PageTableFlags, PhysFrame, Size4KiB, Translate,
+};
+use x86_64::{PhysAddr, VirtAddr};
+
+/// Page table mapper and frame allocator, available after `init`.
+struct Memory {
+    mapper: OffsetPageTable<'static>,
+    frame_allocator: BootInfoFrameAllocator,
+}
+
+static MEMORY: Mutex<Option<Memory>> = Mutex::new(None);
+
+/// Must be called once, before any other function of this module.
+/// Requires the bootloader to map the complete physical memory (`map_physical_memory` feature).
+pub fn init(boot_info: &'static BootInfo) {
+    let physical_memory_offset = VirtAddr::new(boot_info.physical_memory_offset);
+    let memory = unsafe {
+        Memory {
+            mapper: OffsetPageTable::new(active_level_4_table(physical_memory_offset), physical_memory_offset),
+            frame_allocator: BootInfoFrameAllocator::init(&boot_info.memory_map),
+        }
+    };
+    *MEMORY.lock() = Some(memory);
+}
+
+/// Returns the level 4 table the CPU currently uses (CR3), through the physical memory mapping.
+unsafe fn active_level_4_table(physical_memory_offset: VirtAddr) -> &'static mut PageTable {
+    let (level_4_table_frame, _) = Cr3::read();
+
+    let phys = level_4_table_frame.start_address();
+    let virt = physical_memory_offset + phys.as_u64();
+    let page_table_ptr: *mut PageTable = virt.as_mut_ptr();
+
+    &mut *page_table_ptr
+}
+
+fn with_memory<R>(f: impl FnOnce(&mut Memory) -> R) -> R {
+    let mut memory = MEMORY.lock();
+    f(memory.as_mut().expect("memory::init was not called"))
+}
+
+/// Translates a virtual address to the physical address it is mapped to.
+pub fn translate(addr: VirtAddr) -> Option<PhysAddr> {
+    with_memory(|memory| memory.mapper.translate_addr(addr))
+}
+
+/// Maps `page` to `frame`, page tables are allocated from the boot memory map when needed.
+///
+/// Unsafe because the caller must make sure that `frame` is not used anywhere else,
+/// otherwise the same memory becomes reachable through two mutable paths.
+pub unsafe fn map_to(page: Page, frame: PhysFrame, flags: PageTableFlags) -> Result<(), MapToError<Size4KiB>> {
+    with_memory(|memory| {
+        memory
+            .mapper
+            .map_to(page, frame, flags, &mut memory.frame_allocator)?
+            .flush();
+        Ok(())
+    })
+}
+
+/// Maps `page` to a newly allocated frame.
+pub fn map_page(page: Page, flags: PageTableFlags) -> Result<(), MapToError<Size4KiB>> {
+    let frame = allocate_frame().ok_or(MapToError::FrameAllocationFailed)?;
+    // a fresh frame is not used anywhere else
+    unsafe { map_to(page, frame, flags) }
+}
+
+pub fn allocate_frame() -> Option<PhysFrame> {
+    with_memory(|memory| memory.frame_allocator.allocate_frame())
+}
+
+/// Hands out the usable frames of the memory map passed by the bootloader.
+pub struct BootInfoFrameAllocator {
+    memory_map: &'static MemoryMap,
+    next: usize,
+}
+
+impl BootInfoFrameAllocator {
+    /// Unsafe because the caller must guarantee that the memory map is valid,
+    /// i.e. all frames marked as `Usable` are really unused.
+    pub unsafe fn init(memory_map: &'static MemoryMap) -> BootInfoFrameAllocator {
+        BootInfoFrameAllocator { memory_map, next: 0 }
+    }
+
+    fn usable_frames(&self) -> impl Iterator<Item = PhysFrame> {
+        self.memory_map
+            .iter()
+            .filter(|region| region.region_type == MemoryRegionType::Usable)
+            .map(|region| region.range.start_addr()..region.range.end_addr())
+            .flat_map(|range| range.step_by(4096))
+            .map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
+    }
+}
+
+unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
+    fn allocate_frame(&mut self) -> Option<PhysFrame> {
+        let frame = self.usable_frames().nth(self.next);
+        self.next += 1;
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn translate_identity_mapped_vga_buffer() {
+        let addr = VirtAddr::new(0xb8000);
+        assert_eq!(translate(addr), Some(PhysAddr::new(0xb8000)));
+    }
+
+    #[test_case]
+    fn map_page_makes_page_writable() {
+        let page: Page = Page::containing_address(VirtAddr::new(0xdead_b000));
+        map_page(page, PageTableFlags::PRESENT | PageTableFlags::WRITABLE).unwrap();
+
+        let ptr: *mut u64 = page.start_address().as_mut_ptr();
+        unsafe {
+            ptr.write_volatile(0xf021_f077_f065_f04e);
+            assert_eq!(ptr.read_volatile(), 0xf021_f077_f065_f04e);
+        }
+        assert!(translate(page.start_address()).is_some());
+    }
+}
@@ -0,0 +1,77 @@
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// Lock-free byte queue for one producer (an interrupt handler) and one consumer (the main loop).
+/// Neither side ever waits, so it is safe to push from interrupt context.
+pub struct RingBuffer<const N: usize> {
+    slots: [AtomicU8; N],
+    // index of the next byte to pop, only written by the consumer
+    head: AtomicUsize,
+    // index of the next free slot, only written by the producer
+    tail: AtomicUsize,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    pub const fn new() -> Self {
+        RingBuffer {
+            slots: [const { AtomicU8::new(0) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends a byte, returns `false` and drops it if the queue is full.
+    pub fn push(&self, byte: u8) -> bool {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let next = (tail + 1) % N;
+        if next == self.head.load(Ordering::Acquire) {
+            return false;
+        }
+        self.slots[tail].store(byte, Ordering::Relaxed);
+        self.tail.store(next, Ordering::Release);
+        true
+    }
+
+    pub fn pop(&self) -> Option<u8> {
+        let head = self.head.load(Ordering::Relaxed);
+        if head == self.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let byte = self.slots[head].load(Ordering::Relaxed);
+        self.head.store((head + 1) % N, Ordering::Release);
+        Some(byte)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn pops_in_push_order() {
+        let queue: RingBuffer<4> = RingBuffer::new();
+        assert_eq!(queue.pop(), None);
+        assert!(queue.push(1));
+        assert!(queue.push(2));
+
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test_case]
+    fn full_queue_drops_bytes() {
+        // one slot stays free to tell a full queue from an empty one
+        let queue: RingBuffer<4> = RingBuffer::new();
+        for byte in 0..3 {
+            assert!(queue.push(byte));
+        }
+        assert!(!queue.push(3));
+
+        assert_eq!(queue.pop(), Some(0));
+        assert!(queue.push(4));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(4));
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,187 @@
+use core::fmt;
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::instructions::port::Port;
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 0x80;
+const STATUS_B_24_HOUR: u8 = 0x02;
+const STATUS_B_BINARY: u8 = 0x04;
+const HOUR_PM: u8 = 0x80;
+
+/// Wall-clock time as kept by the RTC, usually local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+/// The registers as read, before the BCD and 12 hour conversions.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct RawTime {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+}
+
+fn read_register(register: u8) -> u8 {
+    let mut address: Port<u8> = Port::new(CMOS_ADDRESS);
+    let mut data: Port<u8> = Port::new(CMOS_DATA);
+    // the address and data accesses must not be split by another reader, e.g. the timer interrupt
+    without_interrupts(|| unsafe {
+        address.write(register);
+        data.read()
+    })
+}
+
+fn update_in_progress() -> bool {
+    read_register(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0
+}
+
+fn read_raw() -> RawTime {
+    RawTime {
+        second: read_register(REG_SECONDS),
+        minute: read_register(REG_MINUTES),
+        hour: read_register(REG_HOURS),
+        day: read_register(REG_DAY),
+        month: read_register(REG_MONTH),
+        year: read_register(REG_YEAR),
+    }
+}
+
+fn from_bcd(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+fn convert(raw: RawTime, status_b: u8) -> DateTime {
+    let binary = status_b & STATUS_B_BINARY != 0;
+    let decode = |value: u8| if binary { value } else { from_bcd(value) };
+
+    // in 12 hour mode the PM flag is in the top bit of the hour, 12 AM is midnight
+    let pm = raw.hour & HOUR_PM != 0;
+    let mut hour = decode(raw.hour & !HOUR_PM);
+    if status_b & STATUS_B_24_HOUR == 0 {
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    DateTime {
+        // the century register is not reliably present, assume the 21st century
+        year: 2000 + decode(raw.year) as u16,
+        month: decode(raw.month),
+        day: decode(raw.day),
+        hour,
+        minute: decode(raw.minute),
+        second: decode(raw.second),
+    }
+}
+
+/// Reads the clock, waiting for an update in progress to finish.
+pub fn read() -> DateTime {
+    let read_after_update = || {
+        while update_in_progress() {
+            core::hint::spin_loop();
+        }
+        read_raw()
+    };
+
+    // an update may still start while reading, so read until two reads agree
+    let mut last = read_after_update();
+    loop {
+        let raw = read_after_update();
+        if raw == last {
+            return convert(raw, read_register(REG_STATUS_B));
+        }
+        last = raw;
+    }
+}
+
+/// Reads the clock without waiting, returns `None` if it is being updated.
+/// Used from the timer interrupt.
+pub fn try_read() -> Option<DateTime> {
+    if update_in_progress() {
+        return None;
+    }
+    let raw = read_raw();
+    // the update may have started while reading
+    if update_in_progress() {
+        return None;
+    }
+    Some(convert(raw, read_register(REG_STATUS_B)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(hour: u8) -> RawTime {
+        RawTime { second: 0x59, minute: 0x30, hour, day: 0x18, month: 0x10, year: 0x26 }
+    }
+
+    #[test_case]
+    fn converts_bcd_24_hour() {
+        let time = convert(raw(0x23), STATUS_B_24_HOUR);
+        assert_eq!(
+            time,
+            DateTime { year: 2026, month: 10, day: 18, hour: 23, minute: 30, second: 59 }
+        );
+    }
+
+    #[test_case]
+    fn converts_binary() {
+        let raw = RawTime { second: 59, minute: 30, hour: 23, day: 18, month: 10, year: 26 };
+        let time = convert(raw, STATUS_B_24_HOUR | STATUS_B_BINARY);
+        assert_eq!((time.year, time.month, time.hour, time.second), (2026, 10, 23, 59));
+    }
+
+    #[test_case]
+    fn converts_12_hour() {
+        assert_eq!(convert(raw(0x12), 0).hour, 0);
+        assert_eq!(convert(raw(0x01), 0).hour, 1);
+        assert_eq!(convert(raw(HOUR_PM | 0x12), 0).hour, 12);
+        assert_eq!(convert(raw(HOUR_PM | 0x11), 0).hour, 23);
+    }
+
+    #[test_case]
+    fn formats_date_and_time() {
+        let time = convert(raw(0x09), STATUS_B_24_HOUR);
+        assert_eq!(alloc::format!("{}", time), "2026-10-18 09:30:59");
+    }
+
+    #[test_case]
+    fn reads_a_valid_date() {
+        let time = read();
+        assert!((1..=12).contains(&time.month));
+        assert!((1..=31).contains(&time.day));
+        assert!(time.hour < 24 && time.minute < 60 && time.second < 60);
+    }
+}
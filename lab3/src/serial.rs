@@ -0,0 +1,109 @@
+use core::fmt;
+use lazy_static::lazy_static;
+use spin::Mutex;
+use x86_64::instructions::{interrupts::without_interrupts, port::Port};
+
+#[macro_export]
+macro_rules! serial_print {
+    ($($arg:tt)*) => ($crate::serial::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! serial_println {
+    () => ($crate::serial_print!("\n"));
+    ($($arg:tt)*) => ($crate::serial_print!("{}\n", format_args!($($arg)*)));
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    without_interrupts(|| {
+        SERIAL1.lock().write_fmt(args).unwrap();
+    });
+}
+
+const COM1_BASE: u16 = 0x3F8;
+
+// line status register bits
+const LSR_DATA_READY: u8 = 0x01;
+const LSR_TRANSMITTER_EMPTY: u8 = 0x20;
+
+lazy_static! {
+    pub static ref SERIAL1: Mutex<SerialPort> = Mutex::new(
+        {
+            let mut serial_port = SerialPort::new(COM1_BASE);
+            serial_port.init();
+            serial_port
+        }
+    );
+}
+
+/// 16550 UART driven through its I/O ports.
+pub struct SerialPort {
+    data: Port<u8>,
+    int_en: Port<u8>,
+    fifo_ctrl: Port<u8>,
+    line_ctrl: Port<u8>,
+    modem_ctrl: Port<u8>,
+    line_sts: Port<u8>,
+}
+
+impl fmt::Write for SerialPort {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.send(byte);
+        }
+        Ok(())
+    }
+}
+
+impl SerialPort {
+    pub const fn new(base: u16) -> SerialPort {
+        SerialPort {
+            data: Port::new(base),
+            int_en: Port::new(base + 1),
+            fifo_ctrl: Port::new(base + 2),
+            line_ctrl: Port::new(base + 3),
+            modem_ctrl: Port::new(base + 4),
+            line_sts: Port::new(base + 5),
+        }
+    }
+
+    /// Configures 38400 baud 8N1 with FIFOs and enables the "data received" interrupt.
+    pub fn init(&mut self) {
+        unsafe {
+            self.int_en.write(0x00);
+
+            // set DLAB and write the divisor 3 (115200 / 3 = 38400 baud)
+            self.line_ctrl.write(0x80);
+            self.data.write(0x03);
+            self.int_en.write(0x00);
+
+            // 8 bits, no parity, one stop bit, DLAB cleared
+            self.line_ctrl.write(0x03);
+            // enable and clear FIFOs, 14 byte threshold
+            self.fifo_ctrl.write(0xC7);
+            // DTR, RTS and OUT2 (OUT2 routes the UART interrupt to the PIC)
+            self.modem_ctrl.write(0x0B);
+            self.int_en.write(0x01);
+        }
+    }
+
+    fn line_status(&mut self) -> u8 {
+        unsafe { self.line_sts.read() }
+    }
+
+    pub fn send(&mut self, byte: u8) {
+        while self.line_status() & LSR_TRANSMITTER_EMPTY == 0 {
+            core::hint::spin_loop();
+        }
+        unsafe { self.data.write(byte) }
+    }
+
+    pub fn try_receive(&mut self) -> Option<u8> {
+        if self.line_status() & LSR_DATA_READY == 0 {
+            return None;
+        }
+        Some(unsafe { self.data.read() })
+    }
+}
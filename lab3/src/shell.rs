@@ -1,379 +1,1051 @@
-use crate::vga_buf::SCREEN;
-use crate::{print, println};
+use crate::vga_buf::{self, Screen, BUF_WIDTH, CONSOLES, CONSOLE_COUNT, TEXT_HEIGHT};
+use crate::game_of_life;
+use crate::fs::{path, ramfs::RamFs, FsError, InodeId, InodeKind, Metadata, Vfs};
+use crate::{print, println, serial_print};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::lazy_static;
 use pc_keyboard::DecodedKey;
+use futures_util::{pin_mut, StreamExt};
+use crate::task::keyboard::{self, KeyPress};
+use commands::find_command;
+use history::History;
+use line_editor::LineEditor;
+use pc_keyboard::KeyCode;
+use tokenizer::tokenize;
+use x86_64::instructions::interrupts::without_interrupts;
+
+mod commands;
+mod completion;
+mod history;
+mod line_editor;
+mod tokenizer;
 
 const FORMATING_STRING: &str = " $ ";
-const FORMATING_STRING_LENGTH: u32 = 3;
-const MAX_COUNT_CHILDREN_DIRECTORIES: usize = 20;
-const MAX_COUNT_DIRECTORIES: usize = 100;
-const DELETED_INDEX_DIRECTORY: usize = MAX_COUNT_DIRECTORIES + 1;
-const MAX_SIZE_DIRECTORY_NAME: usize = 10;
-const COMMAND_SIZE: usize = 10;
-const ARGV_SIZE: usize = 70;
+/// Message tags, colored with ANSI escapes so they also show on the serial console.
+pub const ERROR: &str = "\x1b[31m[Error]\x1b[0m";
+pub const OK: &str = "\x1b[32m[Ok]\x1b[0m";
+/// Lines Shift+PageUp / Shift+PageDown scroll the view by.
+const SCROLL_PAGE: usize = TEXT_HEIGHT as usize / 2;
+
 
+/// The filesystem is shared by the shells of all consoles, only the shell state is per console.
+type SharedFs = Arc<spin::Mutex<dyn Vfs + Send>>;
+
+fn new_filesystem() -> SharedFs {
+    Arc::new(spin::Mutex::new(RamFs::new()))
+}
 
 lazy_static! {
-    static ref SH: spin::Mutex<Shell> = spin::Mutex::new({
-        let mut sh = Shell::new();
-        sh
-    });
+    /// A shell for every console, all working on the same filesystem.
+    static ref SHELLS: [spin::Mutex<Shell>; CONSOLE_COUNT] = {
+        let fs = new_filesystem();
+        core::array::from_fn(|console| spin::Mutex::new(Shell::new(console, fs.clone())))
+    };
+}
+
+/// Shell task, handles keys as they arrive until the kernel stops.
+pub async fn run() {
+    let keys = keyboard::keys();
+    pin_mut!(keys);
+    while let Some(key) = keys.next().await {
+        handle_key(key);
+    }
+}
+
+/// Passes the key to the shell of the shown console, unless it switches or scrolls the console.
+fn handle_key(press: KeyPress) {
+    if press.modifiers.alt {
+        if let Some(console) = console_for_key(press.key) {
+            return switch_console(console);
+        }
+    }
+
+    let console = vga_buf::active_console();
+    let screen = &CONSOLES[console];
+    if press.modifiers.shift {
+        match press.key {
+            DecodedKey::RawKey(KeyCode::PageUp) => {
+                return without_interrupts(|| screen.lock().scroll_view_up(SCROLL_PAGE));
+            }
+            DecodedKey::RawKey(KeyCode::PageDown) => {
+                return without_interrupts(|| screen.lock().scroll_view_down(SCROLL_PAGE));
+            }
+            _ => {}
+        }
+    }
+    // any other key returns to the live screen
+    without_interrupts(|| screen.lock().show_live());
+
+    let shell = &SHELLS[console];
+    let key = press.key;
+    if game_of_life::is_running_on(console) {
+        if game_of_life::is_exit_key(key) {
+            game_of_life::stop();
+            shell.lock().print_prompt();
+        }
+        return;
+    }
+
+    match key {
+        // the input is stored as single bytes, characters outside Latin-1 can not be shown by the VGA text mode
+        DecodedKey::Unicode(c) if (c as u32) <= 0xFF => shell.lock().on_key_pressed(c as u8),
+        DecodedKey::Unicode(_) => {}
+        DecodedKey::RawKey(code) => shell.lock().on_raw_key(code),
+    }
 }
 
-pub fn handle_keyboard_interrupt(key: DecodedKey) {
+/// Alt+F1 shows the first console, Alt+F2 the second and so on.
+fn console_for_key(key: DecodedKey) -> Option<usize> {
     match key {
-        DecodedKey::Unicode(c) => SH.lock().on_key_pressed(c as u8),
-        DecodedKey::RawKey(rk) => {}
+        DecodedKey::RawKey(KeyCode::F1) => Some(0),
+        DecodedKey::RawKey(KeyCode::F2) => Some(1),
+        DecodedKey::RawKey(KeyCode::F3) => Some(2),
+        DecodedKey::RawKey(KeyCode::F4) => Some(3),
+        _ => None,
+    }
+}
+
+/// Shows `console`, its shell prints the first prompt the first time it is shown.
+fn switch_console(console: usize) {
+    vga_buf::switch_console(console);
+    let mut shell = SHELLS[console].lock();
+    if !shell.prompted {
+        shell.print_prompt();
     }
 }
 
 pub fn init_shell() {
-    good_formatting();
+    SHELLS[vga_buf::active_console()].lock().print_prompt();
 }
 
 // REGION of MY METHODS
 
-#[derive(Debug, Clone, Copy)]
-struct Directory {
-    index: usize,
-    name: [u8; MAX_SIZE_DIRECTORY_NAME],
-    parent_index: usize,
-    child_count: usize,
-    child_indexes: [usize; MAX_COUNT_CHILDREN_DIRECTORIES],
+fn good_formatting() {
+    print!("{}", FORMATING_STRING);
+}
+
+// END REGION of MY METHODS
+
+struct Shell {
+    /// The console the shell reads keys from and prints to.
+    console: usize,
+    editor: LineEditor,
+    fs: SharedFs,
+    current_directory: InodeId,
+    history: History,
+    /// Entry shown while browsing the history with the arrow keys.
+    history_index: Option<usize>,
+    /// Input typed before browsing started, restored when going past the newest entry.
+    draft: String,
+    /// Screen position right after the prompt, where the input starts.
+    input_start: (u32, u32),
+    /// Width of the console's text area, where the input wraps.
+    input_width: u32,
+    /// A second Tab in a row lists the candidates of an ambiguous completion.
+    last_key_was_tab: bool,
+    /// Whether the first prompt was printed, consoles get theirs when they are first shown.
+    prompted: bool,
+}
+
+/// Returns the argument at `index`, missing arguments are empty.
+fn arg(argv: &[String], index: usize) -> &str {
+    argv.get(index).map(String::as_str).unwrap_or("")
 }
 
-struct DirectoryList {
-    directories: [Directory; MAX_COUNT_DIRECTORIES],
-    directory_count: usize
+fn print_error(err: FsError) {
+    print!("\n{} {}", ERROR, err);
 }
 
-pub fn mu_split(arr: [u8; 80], buf_len: usize) -> ([u8; COMMAND_SIZE], [u8; ARGV_SIZE]) {
-    let mut cmd: [u8; COMMAND_SIZE] = [b'\0'; COMMAND_SIZE];
-    let mut argument: [u8; ARGV_SIZE] = [b'\0'; ARGV_SIZE];
+fn directory_tree(fs: &dyn Vfs, directory: InodeId, tab_count: usize) {
+    let children = match fs.children(directory) {
+        Ok(children) => children,
+        Err(_) => return,
+    };
 
-    let mut i = 0;
+    for &child in children {
+        for _ in 0..tab_count {
+            print!("    ");
+        }
+
+        let name = fs.name(child).unwrap_or("");
+        match fs.metadata(child) {
+            Ok(Metadata { kind: InodeKind::File, size }) => println!("{} ({} bytes)", name, size),
+            _ => println!("/{}", name),
+        }
 
-    while arr[i] != b' ' && i < COMMAND_SIZE {
-        cmd[i] = arr[i];
-        i += 1;
+        directory_tree(fs, child, tab_count + 1);
     }
+}
 
-    if i == buf_len {
-        return (cmd, argument);
+impl Shell {
+    /// Looks the command up in the registry, checks the argument count and runs it.
+    fn execute_command(&mut self, argv: &[String]) {
+        let command = match find_command(arg(argv, 0)) {
+            Some(command) => command,
+            None => {
+                println!();
+                print!("{} Command \"{}\" not found!", ERROR, arg(argv, 0));
+                return;
+            }
+        };
+
+        let arg_count = argv.len() - 1;
+        if arg_count < command.min_args || arg_count > command.max_args {
+            print!("\n{} Usage: {}", ERROR, command.usage);
+            return;
+        }
+
+        (command.handler)(self, argv);
     }
 
-    i += 1;
+    /// Resolves `path` relative to the current directory.
+    fn resolve(&self, fs: &dyn Vfs, path: &str) -> Result<InodeId, FsError> {
+        path::resolve(fs, self.current_directory, path)
+    }
 
-    let mut j = 0;
-    while i < buf_len {
-        argument[j] = arr[i];
-        i += 1;
-        j += 1;
+    /// Resolves the directory that contains the last component of `path`.
+    fn resolve_parent<'a>(&self, fs: &dyn Vfs, path: &'a str) -> Result<(InodeId, &'a str), FsError> {
+        path::resolve_parent(fs, self.current_directory, path)
     }
 
-    return (cmd, argument);
-}
+    fn delete_directory_command(&mut self, dir_path: &str)
+    {
+        let mut fs = self.fs.lock();
+        let (parent, dir_name) = match self.resolve_parent(&*fs, dir_path) {
+            Ok(entry) => entry,
+            Err(err) => return print_error(err),
+        };
 
-pub fn compare_str_with_arr(str_for_compare: &str, arr: [u8; COMMAND_SIZE]) -> bool {
-    let mut are_the_same = true;
+        if fs.lookup(parent, dir_name) == Ok(self.current_directory)
+        {
+            // non-empty ancestors are rejected by the filesystem, but the current directory may be empty
+            print!("\n{} The current directory can not be removed", ERROR);
+            return;
+        }
 
-    let mut i = 0;
-    for symbol in str_for_compare.bytes() {
-        if symbol != arr[i] {
-            are_the_same = false;
+        if let Err(err) = fs.remove_dir(parent, dir_name)
+        {
+            print_error(err);
         }
-        i += 1;
     }
-    return are_the_same;
-}
 
-fn good_formatting() {
-    print!("{}", FORMATING_STRING);
-}
+    fn change_directory_command(&mut self, dir_path: &str) {
+        let target = if dir_path.is_empty() { "/" } else { dir_path };
 
-// END REGION of MY METHODS
+        let fs = self.fs.lock();
+        match self.resolve(&*fs, target) {
+            Ok(dir) if fs.children(dir).is_ok() => self.current_directory = dir,
+            Ok(_) => print_error(FsError::NotADirectory),
+            Err(FsError::NotFound) => print!("\nFolder \"{}\" is not exist!", dir_path),
+            Err(err) => print_error(err),
+        }
+    }
 
-struct Shell {
-    buf: [u8; 80],
-    buf_len: usize,
-    directory_list: DirectoryList,
-    current_directory: Directory,
-}
+    fn clear_command(&mut self) {
+        without_interrupts(|| self.screen().lock().clear());
+    }
 
-impl Shell {
-    fn execute_command(&mut self, argv: ([u8; COMMAND_SIZE], [u8; ARGV_SIZE])) {
-        if compare_str_with_arr("cur_dir", argv.0) {
-            self.current_directory_command(self.current_directory);
-        } 
-        else if compare_str_with_arr("make_dir", argv.0) {
-            self.create_folder_command(argv.1);
-        } 
-        else if compare_str_with_arr("clear", argv.0) {
-            self.clear_command();
-        } 
-        else if compare_str_with_arr("change_dir", argv.0) {
-            self.change_directory_command(argv.1);
-        } 
-        else if compare_str_with_arr("dir_tree", argv.0) {
-            self.directory_tree_command(self.directory_list.directories[self.current_directory.index], 0);
-        } 
-        else if compare_str_with_arr("remove_dir", argv.0) {
-            self.delete_directory_command(argv.1);
-        } 
-        else {
-            println!();
-            print!("[Error] Command \"{}\" not found!", core::str::from_utf8(&argv.0).unwrap().trim_matches('\0'));
-        }
-    }
-
-
-    fn delete_directory_command(&mut self, dir_name: [u8; ARGV_SIZE])
-    {
-        let cur_dir = self.directory_list.directories[self.current_directory.index];
-        let mut is_same = true;
-        for i in 0..cur_dir.child_count
-        {
-            let dir_to_check = self.directory_list.directories[cur_dir.child_indexes[i]];
-            
-            is_same = true;
-
-            for j in 0..MAX_SIZE_DIRECTORY_NAME
-            {
-                if dir_to_check.name[j] != dir_name[j]
-                {
-                    is_same = false;
-                }
+    fn dir_tree_command(&self, dir_path: &str) {
+        let fs = self.fs.lock();
+        match self.resolve(&*fs, dir_path) {
+            Ok(dir) if fs.children(dir).is_ok() => {
+                println!();
+                directory_tree(&*fs, dir, 0);
             }
+            Ok(_) => print_error(FsError::NotADirectory),
+            Err(err) => print_error(err),
+        }
+    }
 
-            if is_same
-            {        
-                if self.directory_list.directories[dir_to_check.index].child_count > 0
-                {
-                    print!("[Error] Count parents must be 0");
-                    return;
-                }
-                
-                self.directory_list.directories[self.current_directory.index].child_count -= 1;
+    fn touch_command(&mut self, file_path: &str) {
+        let mut fs = self.fs.lock();
+        let result = self
+            .resolve_parent(&*fs, file_path)
+            .and_then(|(parent, file_name)| fs.create_file(parent, file_name));
 
-                self.directory_list.directories[dir_to_check.index] = Directory {
-                    index: DELETED_INDEX_DIRECTORY,
-                    name: [b' '; MAX_SIZE_DIRECTORY_NAME],
-                    parent_index: DELETED_INDEX_DIRECTORY,
-                    child_count: DELETED_INDEX_DIRECTORY,
-                    child_indexes: [DELETED_INDEX_DIRECTORY; MAX_COUNT_CHILDREN_DIRECTORIES],
-                };
+        match result {
+            Ok(_) | Err(FsError::AlreadyExists) => {}
+            Err(err) => print_error(err),
+        }
+    }
 
-                self.directory_list.directories[cur_dir.index].child_indexes[i] = DELETED_INDEX_DIRECTORY;
+    /// Writes `text` as a line to the file, replacing or extending the content.
+    /// The file is created if it does not exist.
+    fn write_command(&mut self, file_path: &str, text: &str, append: bool) {
+        let mut fs = self.fs.lock();
+        let (parent, file_name) = match self.resolve_parent(&*fs, file_path) {
+            Ok(entry) => entry,
+            Err(err) => return print_error(err),
+        };
 
-                return;
-            }
+        let file = match fs.lookup(parent, file_name) {
+            Ok(file) => file,
+            Err(FsError::NotFound) => match fs.create_file(parent, file_name) {
+                Ok(file) => file,
+                Err(err) => return print_error(err),
+            },
+            Err(err) => return print_error(err),
+        };
+
+        let mut line = String::from(text);
+        line.push('\n');
+
+        let result = if append {
+            fs.append(file, line.as_bytes())
+        } else {
+            fs.write(file, line.as_bytes())
+        };
+        if let Err(err) = result {
+            print_error(err);
         }
     }
 
-    fn change_directory_command(&mut self, argv: [u8; ARGV_SIZE]) {
-        if argv[0] == b'.' {
-            self.current_directory =
-                self.directory_list.directories[self.current_directory.parent_index];
-            return;
+    fn cat_command(&mut self, file_path: &str) {
+        let fs = self.fs.lock();
+        let content = self
+            .resolve(&*fs, file_path)
+            .and_then(|file| fs.read(file));
+
+        match content {
+            Ok(content) => match core::str::from_utf8(content) {
+                Ok(text) => print!("\n{}", text.trim_end_matches('\n')),
+                Err(_) => print!("\n{} File is not valid UTF-8", ERROR),
+            },
+            Err(err) => print_error(err),
         }
+    }
 
-        let cur_dir = self.directory_list.directories[self.current_directory.index];
+    fn remove_file_command(&mut self, file_path: &str) {
+        let mut fs = self.fs.lock();
+        let result = self
+            .resolve_parent(&*fs, file_path)
+            .and_then(|(parent, file_name)| fs.remove_file(parent, file_name));
 
-        for dir_index in cur_dir.child_indexes {
-            let mut is_same = true;
-            for i in 0..ARGV_SIZE {
-                if argv[i] == b'\0' {
-                    break;
-                }
+        if let Err(err) = result {
+            print_error(err);
+        }
+    }
 
-                if i == MAX_SIZE_DIRECTORY_NAME {
-                    print!("[Error] The maximum size of the directory name is 10 characters");
-                    return;
-                }
+    fn size_command(&mut self, name: &str) {
+        let fs = self.fs.lock();
+        let metadata = self
+            .resolve(&*fs, name)
+            .and_then(|inode| fs.metadata(inode));
 
-                if self.directory_list.directories[dir_index].name[i] != argv[i] {
-                    is_same = false;
-                    break;
-                }
-            }
+        match metadata {
+            Ok(Metadata { kind: InodeKind::File, size }) => print!("\n{}: {} bytes", name, size),
+            Ok(Metadata { kind: InodeKind::Directory, size }) => print!("\n{}: {} entries", name, size),
+            Err(err) => print_error(err),
+        }
+    }
 
-            if is_same {
-                self.current_directory = self.directory_list.directories[dir_index];
-                return;
-            }
+    fn create_folder_command(&mut self, dir_path: &str) {
+        let mut fs = self.fs.lock();
+        let result = self
+            .resolve_parent(&*fs, dir_path)
+            .and_then(|(parent, dir_name)| fs.create_dir(parent, dir_name));
+
+        match result {
+            Ok(_) => print!("\n{} Directory \"{}\" created succsessfully!", OK, dir_path),
+            Err(err) => print_error(err),
+        }
+    }
+
+    /// Builds the absolute path of a directory by walking up to the root.
+    fn directory_path(&self, directory: InodeId) -> String {
+        let fs = self.fs.lock();
+        let mut names: Vec<&str> = Vec::new();
+        let mut current = directory;
+        while current != fs.root() {
+            names.push(fs.name(current).unwrap_or(""));
+            current = match fs.parent(current) {
+                Ok(parent) => parent,
+                Err(_) => break,
+            };
+        }
+
+        if names.is_empty() {
+            return String::from("/");
         }
 
-        print!(
-            "\nFolder \"{}\" is not exist!",
-            core::str::from_utf8(&argv.clone())
-                .unwrap()
-                .trim_matches('\0')
-        )
+        let mut path = String::new();
+        for name in names.iter().rev() {
+            path.push('/');
+            path.push_str(name);
+        }
+        path
     }
 
-    fn clear_command(&mut self) {
-        SCREEN.lock().clear();
+    fn current_directory_command(&mut self) {
+        print!("\n{}", self.directory_path(self.current_directory));
     }
 
-    fn directory_tree_command(&mut self, current_directory: Directory, tab_count: usize) {
-        println!();
-        for i in 0..current_directory.child_count {
-            let child_directory =
-                self.directory_list.directories[current_directory.child_indexes[i]];
+    pub fn new(console: usize, fs: SharedFs) -> Shell {
+        let root = fs.lock().root();
+        Shell {
+            console,
+            editor: LineEditor::new(),
+            fs,
+            current_directory: root,
+            history: History::new(),
+            history_index: None,
+            draft: String::new(),
+            input_start: (0, 0),
+            input_width: BUF_WIDTH,
+            last_key_was_tab: false,
+            prompted: false,
+        }
+    }
 
-            for tc in 0..tab_count {
-                for ts in 0..4 {
-                    print!(" ");
-                }
-            }
-            print!(
-                "/{}",
-                core::str::from_utf8(&child_directory.name)
-                    .unwrap()
-                    .trim_matches('\0')
-            );
+    fn screen(&self) -> &'static spin::Mutex<Screen> {
+        &CONSOLES[self.console]
+    }
+
+    fn print_prompt(&mut self) {
+        good_formatting();
+        (self.input_start, self.input_width) = without_interrupts(|| {
+            let screen = self.screen().lock();
+            (screen.cursor_position(), screen.width())
+        });
+        self.prompted = true;
+    }
+
+    fn input(&self) -> String {
+        self.editor.text()
+    }
 
-            self.directory_tree_command(child_directory, tab_count + 1);
+    /// Replaces the input with `text` and redraws it.
+    fn set_input(&mut self, text: &str) {
+        let old_len = self.editor.len();
+        self.editor.set(text);
+        self.redraw_input(0, old_len);
+    }
+
+    /// Screen position of the input character at `index`, the input wraps at the text area width.
+    fn input_position(&self, index: usize) -> (u32, u32) {
+        let (line, col) = self.input_start;
+        let offset = col + index as u32;
+        (line + offset / self.input_width, offset % self.input_width)
+    }
+
+    /// Prints the input again starting at index `from`, blanking what is left
+    /// of an input of `old_len` characters, and moves the cursor to the editor cursor.
+    fn redraw_input(&mut self, from: usize, old_len: usize) {
+        // the timer interrupt may draw to the screen too, so it must not fire while the lock is held
+        without_interrupts(|| self.redraw_input_locked(from, old_len));
+        self.mirror_input();
+    }
+
+    /// The input is drawn straight into the screen, so COM1 gets the prompt line redrawn:
+    /// back to its start, the prompt and input, erase the rest and step back to the cursor.
+    fn mirror_input(&self) {
+        serial_print!("\r{}{}\x1b[K", FORMATING_STRING, self.input());
+        let back = self.editor.len() - self.editor.cursor();
+        if back > 0 {
+            serial_print!("\x1b[{}D", back);
         }
     }
 
-    fn print_directory_name(&mut self, dir_name: [u8; MAX_SIZE_DIRECTORY_NAME]) {
-        print!(
-            "{}",
-            core::str::from_utf8(&dir_name).unwrap().trim_matches('\0')
-        );
-        SCREEN.lock().push_row_to_right(0);
-        SCREEN.lock().move_print_to(0);
+    fn redraw_input_locked(&mut self, from: usize, old_len: usize) {
+        let mut screen = self.screen().lock();
+        let (line, col) = self.input_position(from);
+        screen.set_cursor(line, col);
+
+        for &byte in &self.editor.bytes()[from..] {
+            screen.print_byte(byte);
+        }
+        for _ in self.editor.len()..old_len {
+            screen.print_byte(b' ');
+        }
 
-        print!("/");
+        // printing may have scrolled the screen, so find the start again from where it ended
+        let start_col = self.input_start.1;
+        let printed = start_col + self.editor.len().max(old_len) as u32;
+        let (end_line, _) = screen.cursor_position();
+        self.input_start = (end_line.saturating_sub(printed / self.input_width), start_col);
+
+        let (line, col) = self.input_position(self.editor.cursor());
+        screen.set_cursor(line, col);
     }
 
-    fn create_folder_command(&mut self, argv: [u8; ARGV_SIZE]) {
-        let mut name_size = 0;
-        for i in 0..ARGV_SIZE {
-            if argv[i] == b'\0' {
-                break;
+    /// Redraws after an edit that changed the input from index `changed_from`.
+    fn after_edit(&mut self, changed_from: Option<usize>, old_len: usize) {
+        match changed_from {
+            Some(from) => {
+                // editing the recalled entry turns it into a new input
+                self.history_index = None;
+                self.redraw_input(from, old_len);
             }
-            name_size += 1;
+            None => self.move_screen_cursor(),
         }
+    }
 
-        if name_size > MAX_SIZE_DIRECTORY_NAME {
-            print!("\n[Error] The maximum size of the directory name is 10 characters");
-            return;
+    fn move_screen_cursor(&mut self) {
+        let (line, col) = self.input_position(self.editor.cursor());
+        without_interrupts(|| self.screen().lock().set_cursor(line, col));
+        self.mirror_input();
+    }
+
+    fn on_raw_key(&mut self, code: KeyCode) {
+        self.last_key_was_tab = false;
+
+        match code {
+            KeyCode::ArrowUp => self.history_previous(),
+            KeyCode::ArrowDown => self.history_next(),
+            KeyCode::ArrowLeft => {
+                self.editor.move_left();
+                self.move_screen_cursor();
+            }
+            KeyCode::ArrowRight => {
+                self.editor.move_right();
+                self.move_screen_cursor();
+            }
+            KeyCode::Home => {
+                self.editor.move_home();
+                self.move_screen_cursor();
+            }
+            KeyCode::End => {
+                self.editor.move_end();
+                self.move_screen_cursor();
+            }
+            KeyCode::Delete => {
+                let old_len = self.editor.len();
+                let changed_from = self.editor.delete();
+                self.after_edit(changed_from, old_len);
+            }
+            _ => {}
         }
+    }
+
+    /// Completes the word before the cursor: the first word as a command, the others as paths.
+    fn complete(&mut self, second_tab: bool) {
+        let cursor = self.editor.cursor();
+        let before_cursor = &self.editor.bytes()[..cursor];
+        let word_start = before_cursor.iter().rposition(|&byte| byte == b' ').map_or(0, |index| index + 1);
+        let is_command = before_cursor[..word_start].iter().all(|&byte| byte == b' ');
+        let word: String = before_cursor[word_start..].iter().map(|&byte| byte as char).collect();
 
-        let mut directory: Directory = Directory {
-            index: self.directory_list.directory_count,
-            name: [b'\0'; MAX_SIZE_DIRECTORY_NAME],
-            parent_index: self.current_directory.index,
-            child_count: 0,
-            child_indexes: [0; MAX_COUNT_CHILDREN_DIRECTORIES],
+        let completion = if is_command {
+            completion::complete_command(&word)
+        } else {
+            completion::complete_path(&*self.fs.lock(), self.current_directory, &word)
         };
 
-        for i in 0..MAX_SIZE_DIRECTORY_NAME {
-            directory.name[i] = argv[i];
+        let insertion = completion.insertion();
+        if !insertion.is_empty() {
+            let old_len = self.editor.len();
+            for c in insertion.chars() {
+                // stop when the input is full
+                if self.editor.insert(c as u8).is_none() {
+                    break;
+                }
+            }
+            self.after_edit(Some(cursor), old_len);
+        } else if second_tab && completion.candidates.len() > 1 {
+            self.list_candidates(&completion.candidates);
         }
-        let current_directory = self.directory_list.directories[self.current_directory.index];
+    }
+
+    /// Prints the candidates below the input and draws the prompt with the input again.
+    fn list_candidates(&mut self, candidates: &[String]) {
+        let (line, col) = self.input_position(self.editor.len());
+        without_interrupts(|| self.screen().lock().set_cursor(line, col));
 
-        self.directory_list.directories[self.directory_list.directory_count] = directory;
-        self.directory_list.directories[self.current_directory.index].child_indexes[current_directory.child_count] = self.directory_list.directory_count;
+        print!("\n{}", candidates.join("  "));
+        println!();
+        self.print_prompt();
+        self.redraw_input(0, 0);
+    }
 
-        self.directory_list.directory_count += 1;
-        self.directory_list.directories[self.current_directory.index].child_count += 1;
+    fn history_previous(&mut self) {
+        let index = match self.history_index {
+            None if self.history.len() > 0 => {
+                self.draft = self.input();
+                self.history.len() - 1
+            }
+            Some(index) if index > 0 => index - 1,
+            _ => return,
+        };
 
-        print!(
-            "\n[Ok] Directory \"{}\" created succsessfully!",
-            core::str::from_utf8(&directory.name.clone())
-                .unwrap()
-                .trim_matches('\0')
-        );
+        self.history_index = Some(index);
+        let entry = String::from(self.history.at(index).unwrap_or(""));
+        self.set_input(&entry);
     }
 
-    fn current_directory_command(&mut self, current_directory: Directory) -> usize {
-        let parent_directory = self.directory_list.directories[current_directory.parent_index];
-        let mut nesting = 0;
+    fn history_next(&mut self) {
+        let index = match self.history_index {
+            Some(index) => index,
+            None => return,
+        };
 
-        if current_directory.index > 0 {
-            nesting = self.current_directory_command(parent_directory);
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            let entry = String::from(self.history.at(index + 1).unwrap_or(""));
+            self.set_input(&entry);
         } else {
-            println!();
-        }
-
-        print!(
-            "/{}",
-            core::str::from_utf8(&current_directory.name.clone())
-                .unwrap()
-                .trim_matches('\0')
-        );
-
-        return nesting;
-    }
-
-    pub fn new() -> Shell {
-        let mut shell: Shell = Shell {
-            buf: [0; 80],
-            buf_len: 0,
-            directory_list: DirectoryList {
-                directories: ([Directory {
-                    index: 0,
-                    name: [b' '; MAX_SIZE_DIRECTORY_NAME],
-                    parent_index: 0,
-                    child_count: 0,
-                    child_indexes: [DELETED_INDEX_DIRECTORY; MAX_COUNT_CHILDREN_DIRECTORIES],
-                }; MAX_COUNT_DIRECTORIES]),
-                directory_count: 1,
-            },
-            current_directory: Directory {
-                index: 0,
-                name: [
-                    b'r', b'o', b'o', b't', b'\0', b'\0', b'\0', b'\0', b'\0', b'\0',
-                ],
-                parent_index: 0,
-                child_count: 0,
-                child_indexes: [DELETED_INDEX_DIRECTORY; MAX_COUNT_CHILDREN_DIRECTORIES],
-            }     
-        };
+            self.history_index = None;
+            let draft = core::mem::take(&mut self.draft);
+            self.set_input(&draft);
+        }
+    }
 
-        shell.directory_list.directories[0] = shell.current_directory;
+    fn history_command(&mut self) {
+        for (number, line) in self.history.iter() {
+            print!("\n{:>4}  {}", number, line);
+        }
+    }
 
-        return shell;
+    /// Expands `!!` / `!n`, records the line in the history and runs it.
+    fn submit_line(&mut self, line: String) {
+        let line = match self.history.expand(&line) {
+            Ok(Some(expanded)) => {
+                print!("\n{}", expanded);
+                expanded
+            }
+            Ok(None) => line,
+            Err(designator) => {
+                print!("\n{} {}: event not found", ERROR, designator);
+                return;
+            }
+        };
+        self.history.push(&line);
+
+        match tokenize(&line) {
+            Ok(argv) if argv.is_empty() => {}
+            Ok(argv) => self.execute_command(&argv),
+            Err(err) => print!("\n{} {}", ERROR, err),
+        }
     }
 
     pub fn on_key_pressed(&mut self, key: u8) {
-        match key {
+        let old_len = self.editor.len();
+        let second_tab = key == b'\t' && self.last_key_was_tab;
+        self.last_key_was_tab = key == b'\t';
+
+        let changed_from = match key {
             b'\n' => {
-                let argv = mu_split(self.buf, self.buf_len);
+                let line = self.input();
+                // the output goes below the whole input, not below the cursor
+                self.editor.move_end();
+                self.move_screen_cursor();
+                self.editor.clear();
+                self.history_index = None;
 
-                self.execute_command(argv);
-                self.buf_len = 0;
-                println!();
-                good_formatting()
+                self.submit_line(line);
+                // the game owns the screen until it is stopped, the prompt is printed on exit
+                if !game_of_life::is_running_on(self.console) {
+                    println!();
+                    self.print_prompt();
+                }
+                return;
             }
-            8 =>
             // key code of backspace
-            {   
-                SCREEN.lock().delete_last_symbol(FORMATING_STRING_LENGTH);
-
-                if self.buf_len > 0 {
-                    self.buf_len -= 1;
-                }
-                self.buf[self.buf_len] = 0;
+            8 => self.editor.backspace(),
+            // delete, also sent by the Delete key
+            0x7F => self.editor.delete(),
+            // Ctrl+A and Ctrl+E
+            0x01 => {
+                self.editor.move_home();
+                None
             }
-            32 =>
-            // key code of space button
-            {
-                print!("{}", key as char);
-
-                self.buf[self.buf_len] = b' ';
-                self.buf_len += 1;
-                
+            0x05 => {
+                self.editor.move_end();
+                None
             }
-            _ => {
+            // Ctrl+U, Ctrl+K and Ctrl+W
+            0x15 => self.editor.kill_to_start(),
+            0x0B => self.editor.kill_to_end(),
+            0x17 => self.editor.kill_word_back(),
+            b'\t' => return self.complete(second_tab),
+            // other control characters have no binding
+            0x00..=0x1F => return,
+            // a full input ignores further characters
+            _ => self.editor.insert(key),
+        };
+
+        self.after_edit(changed_from, old_len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_argv(line: &str) -> Vec<String> {
+        tokenize(line).unwrap()
+    }
+
+    #[test_case]
+    fn commands_match_exactly() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.execute_command(&to_argv("make_dirx docs"));
+        shell.execute_command(&to_argv("make_di docs"));
+        let root = shell.fs.lock().root();
+        assert_eq!(shell.fs.lock().children(root), Ok(&[][..]));
+
+        shell.execute_command(&to_argv("make_dir docs"));
+        assert!(shell.resolve(&*shell.fs.lock(), "docs").is_ok());
+    }
+
+    #[test_case]
+    fn write_joins_words_and_accepts_quotes() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.execute_command(&to_argv("write notes hello   world"));
+        shell.execute_command(&to_argv("append notes \"quoted   text\""));
+
+        let notes = shell.resolve(&*shell.fs.lock(), "notes").unwrap();
+        assert_eq!(shell.fs.lock().read(notes), Ok(&b"hello world\nquoted   text\n"[..]));
+    }
+
+    #[test_case]
+    fn make_dir_adds_child() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("docs");
+
+        let root = shell.fs.lock().root();
+        let docs = shell.fs.lock().lookup(root, "docs").unwrap();
+        assert_eq!(shell.fs.lock().children(root), Ok(&[docs][..]));
+        assert_eq!(shell.fs.lock().parent(docs), Ok(root));
+    }
+
+    #[test_case]
+    fn make_dir_rejects_long_name() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command(&"x".repeat(crate::fs::MAX_NAME_LENGTH + 1));
+
+        let root = shell.fs.lock().root();
+        assert_eq!(shell.fs.lock().children(root), Ok(&[][..]));
+    }
+
+    #[test_case]
+    fn change_dir_enters_child_and_returns_to_parent() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("docs");
+
+        shell.change_directory_command("docs");
+        let root = shell.fs.lock().root();
+        assert_eq!(shell.current_directory, shell.fs.lock().lookup(root, "docs").unwrap());
+        assert_eq!(shell.directory_path(shell.current_directory), "/docs");
+
+        shell.change_directory_command("..");
+        assert_eq!(shell.current_directory, shell.fs.lock().root());
+        assert_eq!(shell.directory_path(shell.current_directory), "/");
+    }
+
+    #[test_case]
+    fn remove_dir_removes_empty_child() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("docs");
+        shell.delete_directory_command("docs");
+
+        let root = shell.fs.lock().root();
+        assert_eq!(shell.fs.lock().children(root), Ok(&[][..]));
+    }
+
+    #[test_case]
+    fn remove_dir_keeps_non_empty_child() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("docs");
+        shell.change_directory_command("docs");
+        shell.create_folder_command("notes");
+        shell.change_directory_command("..");
+        shell.delete_directory_command("docs");
+
+        let root = shell.fs.lock().root();
+        let docs = shell.fs.lock().lookup(root, "docs").unwrap();
+        assert_eq!(shell.fs.lock().children(docs).map(|children| children.len()), Ok(1));
+    }
+
+    #[test_case]
+    fn write_and_append_lines() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.write_command("notes", "first", false);
+        shell.write_command("notes", "second", true);
+
+        let root = shell.fs.lock().root();
+        let notes = shell.fs.lock().lookup(root, "notes").unwrap();
+        assert_eq!(shell.fs.lock().read(notes), Ok(&b"first\nsecond\n"[..]));
+
+        shell.write_command("notes", "again", false);
+        assert_eq!(shell.fs.lock().read(notes), Ok(&b"again\n"[..]));
+    }
+
+    #[test_case]
+    fn touch_keeps_existing_content() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.write_command("notes", "first", false);
+        shell.touch_command("notes");
+        shell.touch_command("empty");
 
-                self.buf[self.buf_len] = key;
-                self.buf_len += 1;
-                print!("{}", key as char);
+        let root = shell.fs.lock().root();
+        let notes = shell.fs.lock().lookup(root, "notes").unwrap();
+        let empty = shell.fs.lock().lookup(root, "empty").unwrap();
+        assert_eq!(shell.fs.lock().read(notes), Ok(&b"first\n"[..]));
+        assert_eq!(shell.fs.lock().metadata(empty).map(|metadata| metadata.size), Ok(0));
+    }
+
+    #[test_case]
+    fn rm_removes_only_files() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.touch_command("notes");
+        shell.create_folder_command("docs");
+        shell.remove_file_command("notes");
+        shell.remove_file_command("docs");
+
+        let root = shell.fs.lock().root();
+        assert!(shell.fs.lock().lookup(root, "notes").is_err());
+        assert!(shell.fs.lock().lookup(root, "docs").is_ok());
+    }
+
+    #[test_case]
+    fn commands_accept_paths() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("a");
+        shell.create_folder_command("a/b");
+        shell.create_folder_command("/a/b/c");
+        shell.write_command("a/b/notes", "text", false);
+
+        shell.change_directory_command("/a/b/c");
+        assert_eq!(shell.directory_path(shell.current_directory), "/a/b/c");
+
+        shell.create_folder_command("../../x");
+        assert!(shell.resolve(&*shell.fs.lock(), "/a/x").is_ok());
+
+        shell.remove_file_command("../notes");
+        assert!(shell.resolve(&*shell.fs.lock(), "/a/b/notes").is_err());
+
+        shell.change_directory_command("..//.");
+        assert_eq!(shell.directory_path(shell.current_directory), "/a/b");
+
+        shell.change_directory_command("");
+        assert_eq!(shell.current_directory, shell.fs.lock().root());
+    }
+
+    #[test_case]
+    fn change_dir_rejects_files_and_missing_paths() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.touch_command("notes");
+
+        shell.change_directory_command("notes");
+        assert_eq!(shell.current_directory, shell.fs.lock().root());
+        shell.change_directory_command("missing/dir");
+        assert_eq!(shell.current_directory, shell.fs.lock().root());
+    }
+
+    #[test_case]
+    fn remove_dir_keeps_current_directory() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("docs");
+        shell.change_directory_command("docs");
+        shell.delete_directory_command("../docs");
+
+        assert!(shell.resolve(&*shell.fs.lock(), "/docs").is_ok());
+    }
+
+    #[test_case]
+    fn aliases_run_the_same_command() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.execute_command(&to_argv("mkdir docs"));
+        shell.execute_command(&to_argv("cd docs"));
+
+        assert_eq!(shell.directory_path(shell.current_directory), "/docs");
+    }
+
+    #[test_case]
+    fn argument_count_is_checked() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.execute_command(&to_argv("make_dir a b"));
+        shell.execute_command(&to_argv("make_dir"));
+
+        let root = shell.fs.lock().root();
+        assert_eq!(shell.fs.lock().children(root), Ok(&[][..]));
+    }
+
+    #[test_case]
+    fn registry_names_are_unique() {
+        for (i, command) in commands::COMMANDS.iter().enumerate() {
+            assert!(command.min_args <= command.max_args);
+            for other in &commands::COMMANDS[i + 1..] {
+                assert!(!other.matches(command.name));
+                assert!(command.aliases.iter().all(|alias| !other.matches(alias)));
             }
         }
     }
+
+    #[test_case]
+    fn arrows_recall_history_and_restore_draft() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.submit_line(String::from("make_dir docs"));
+        shell.submit_line(String::from("cur_dir"));
+        shell.set_input("dra");
+
+        shell.on_raw_key(KeyCode::ArrowUp);
+        assert_eq!(shell.input(), "cur_dir");
+        shell.on_raw_key(KeyCode::ArrowUp);
+        assert_eq!(shell.input(), "make_dir docs");
+        shell.on_raw_key(KeyCode::ArrowUp);
+        assert_eq!(shell.input(), "make_dir docs");
+
+        shell.on_raw_key(KeyCode::ArrowDown);
+        assert_eq!(shell.input(), "cur_dir");
+        shell.on_raw_key(KeyCode::ArrowDown);
+        assert_eq!(shell.input(), "dra");
+    }
+
+    #[test_case]
+    fn history_designators_rerun_commands() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.submit_line(String::from("make_dir docs"));
+        shell.submit_line(String::from("remove_dir docs"));
+        shell.submit_line(String::from("!1"));
+
+        assert!(shell.resolve(&*shell.fs.lock(), "docs").is_ok());
+        assert_eq!(shell.history.last(), Some("make_dir docs"));
+
+        shell.submit_line(String::from("remove_dir docs"));
+        shell.submit_line(String::from("!!"));
+        shell.submit_line(String::from("!99"));
+        assert_eq!(shell.history.len(), 4);
+    }
+
+    #[test_case]
+    fn line_editing_keys() {
+        let mut shell = Shell::new(0, new_filesystem());
+        for &key in b"mkdir docs" {
+            shell.on_key_pressed(key);
+        }
+        shell.on_raw_key(KeyCode::Home);
+        shell.on_key_pressed(b'x');
+        shell.on_raw_key(KeyCode::Delete);
+        assert_eq!(shell.input(), "xkdir docs");
+
+        // Ctrl+E, Ctrl+W
+        shell.on_key_pressed(0x05);
+        shell.on_key_pressed(0x17);
+        assert_eq!(shell.input(), "xkdir ");
+
+        // Ctrl+A, Ctrl+K
+        shell.on_key_pressed(0x01);
+        shell.on_raw_key(KeyCode::ArrowRight);
+        shell.on_key_pressed(0x0B);
+        assert_eq!(shell.input(), "x");
+        assert_eq!(shell.editor.cursor(), 1);
+    }
+
+    #[test_case]
+    fn long_input_wraps_to_next_row() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.input_start = (0, 3);
+        for _ in 0..BUF_WIDTH {
+            shell.on_key_pressed(b'a');
+        }
+
+        assert_eq!(shell.input_position(shell.editor.cursor()), (1, 3));
+        assert_eq!(shell.screen().lock().cursor_position(), (1, 3));
+    }
+
+    #[test_case]
+    fn tab_completes_commands_and_paths() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("documents");
+        for &key in b"chan\tdoc\t" {
+            shell.on_key_pressed(key);
+        }
+        assert_eq!(shell.input(), "change_dir documents/");
+
+        shell.set_input("ca");
+        shell.on_key_pressed(b'\t');
+        assert_eq!(shell.input(), "cat ");
+    }
+
+    #[test_case]
+    fn second_tab_keeps_ambiguous_input() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.create_folder_command("docs");
+        shell.create_folder_command("downloads");
+        shell.set_input("cd d");
+        shell.on_key_pressed(b'\t');
+        assert_eq!(shell.input(), "cd do");
+
+        shell.on_key_pressed(b'\t');
+        shell.on_key_pressed(b'\t');
+        assert_eq!(shell.input(), "cd do");
+        assert_eq!(shell.editor.cursor(), 5);
+    }
+
+    #[test_case]
+    fn typing_past_the_limit_is_ignored() {
+        let mut shell = Shell::new(0, new_filesystem());
+        for _ in 0..line_editor::MAX_INPUT_LENGTH + 20 {
+            shell.on_key_pressed(b'a');
+        }
+        assert_eq!(shell.editor.len(), line_editor::MAX_INPUT_LENGTH);
+
+        shell.on_key_pressed(b'\n');
+        assert_eq!(shell.editor.len(), 0);
+    }
+
+    #[test_case]
+    fn backspace_on_empty_input_keeps_prompt() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.print_prompt();
+        let prompt_end = shell.screen().lock().cursor_position();
+
+        shell.on_key_pressed(8);
+        shell.on_raw_key(KeyCode::Delete);
+        assert_eq!(shell.screen().lock().cursor_position(), prompt_end);
+        assert_eq!(shell.screen().lock().read_char(prompt_end.0 * BUF_WIDTH + prompt_end.1 - 2).char_byte, b'$');
+    }
+
+    #[test_case]
+    fn backspace_across_wrapped_rows() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.print_prompt();
+        let (line, col) = shell.input_start;
+        for _ in 0..BUF_WIDTH {
+            shell.on_key_pressed(b'a');
+        }
+        for _ in 0..col + 1 {
+            shell.on_key_pressed(8);
+        }
+
+        let (start_line, _) = shell.input_start;
+        assert!(start_line <= line);
+        assert_eq!(shell.screen().lock().cursor_position(), (start_line, BUF_WIDTH - 1));
+    }
+
+    #[test_case]
+    fn tab_without_candidates_does_nothing() {
+        let mut shell = Shell::new(0, new_filesystem());
+        shell.set_input("cat missing/xy");
+        shell.on_key_pressed(b'\t');
+        shell.on_key_pressed(b'\t');
+        assert_eq!(shell.input(), "cat missing/xy");
+    }
+
+    #[test_case]
+    fn function_keys_pick_consoles() {
+        assert_eq!(console_for_key(DecodedKey::RawKey(KeyCode::F1)), Some(0));
+        assert_eq!(console_for_key(DecodedKey::RawKey(KeyCode::F4)), Some(CONSOLE_COUNT - 1));
+        assert_eq!(console_for_key(DecodedKey::RawKey(KeyCode::F5)), None);
+        assert_eq!(console_for_key(DecodedKey::Unicode('1')), None);
+    }
+
+    #[test_case]
+    fn consoles_share_the_filesystem() {
+        let fs = new_filesystem();
+        let mut first = Shell::new(0, fs.clone());
+        let mut second = Shell::new(1, fs);
+        first.create_folder_command("docs");
+        first.change_directory_command("docs");
+        first.write_command("notes", "from tty1", false);
+
+        // the file is visible from the other console, the current directory is not shared
+        let root = second.fs.lock().root();
+        assert_eq!(second.current_directory, root);
+        let notes = second.resolve(&*second.fs.lock(), "docs/notes").unwrap();
+        assert_eq!(second.fs.lock().read(notes), Ok(&b"from tty1\n"[..]));
+    }
 }
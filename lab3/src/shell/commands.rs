@@ -0,0 +1,233 @@
+use super::{arg, Shell, ERROR};
+use crate::game_of_life;
+use crate::rtc;
+use crate::thread;
+use crate::time;
+use crate::{print, println};
+use alloc::string::String;
+
+pub type Handler = fn(&mut Shell, &[String]);
+
+/// A shell command. `argv[0]` is the command name, the argument limits do not count it.
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub min_args: usize,
+    pub max_args: usize,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub handler: Handler,
+}
+
+impl Command {
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+}
+
+/// Joins the arguments starting at `index`, so the text may be quoted or passed as several words.
+fn text_from(argv: &[String], index: usize) -> String {
+    argv.get(index..).unwrap_or(&[]).join(" ")
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        aliases: &[],
+        min_args: 0,
+        max_args: 1,
+        usage: "help [command]",
+        description: "List commands or show the usage of one",
+        handler: |_, argv| help_command(arg(argv, 1)),
+    },
+    Command {
+        name: "cur_dir",
+        aliases: &["pwd"],
+        min_args: 0,
+        max_args: 0,
+        usage: "cur_dir",
+        description: "Print the current directory",
+        handler: |shell, _| shell.current_directory_command(),
+    },
+    Command {
+        name: "change_dir",
+        aliases: &["cd"],
+        min_args: 0,
+        max_args: 1,
+        usage: "change_dir [path]",
+        description: "Change the current directory, to the root without a path",
+        handler: |shell, argv| shell.change_directory_command(arg(argv, 1)),
+    },
+    Command {
+        name: "make_dir",
+        aliases: &["mkdir"],
+        min_args: 1,
+        max_args: 1,
+        usage: "make_dir <path>",
+        description: "Create a directory",
+        handler: |shell, argv| shell.create_folder_command(arg(argv, 1)),
+    },
+    Command {
+        name: "remove_dir",
+        aliases: &["rmdir"],
+        min_args: 1,
+        max_args: 1,
+        usage: "remove_dir <path>",
+        description: "Remove an empty directory",
+        handler: |shell, argv| shell.delete_directory_command(arg(argv, 1)),
+    },
+    Command {
+        name: "dir_tree",
+        aliases: &["tree"],
+        min_args: 0,
+        max_args: 1,
+        usage: "dir_tree [path]",
+        description: "Print the directory tree",
+        handler: |shell, argv| shell.dir_tree_command(arg(argv, 1)),
+    },
+    Command {
+        name: "touch",
+        aliases: &[],
+        min_args: 1,
+        max_args: 1,
+        usage: "touch <path>",
+        description: "Create an empty file",
+        handler: |shell, argv| shell.touch_command(arg(argv, 1)),
+    },
+    Command {
+        name: "write",
+        aliases: &[],
+        min_args: 1,
+        max_args: usize::MAX,
+        usage: "write <path> [text...]",
+        description: "Replace the file content with a line of text",
+        handler: |shell, argv| shell.write_command(arg(argv, 1), &text_from(argv, 2), false),
+    },
+    Command {
+        name: "append",
+        aliases: &[],
+        min_args: 1,
+        max_args: usize::MAX,
+        usage: "append <path> [text...]",
+        description: "Append a line of text to the file",
+        handler: |shell, argv| shell.write_command(arg(argv, 1), &text_from(argv, 2), true),
+    },
+    Command {
+        name: "cat",
+        aliases: &[],
+        min_args: 1,
+        max_args: 1,
+        usage: "cat <path>",
+        description: "Print the file content",
+        handler: |shell, argv| shell.cat_command(arg(argv, 1)),
+    },
+    Command {
+        name: "rm",
+        aliases: &[],
+        min_args: 1,
+        max_args: 1,
+        usage: "rm <path>",
+        description: "Remove a file",
+        handler: |shell, argv| shell.remove_file_command(arg(argv, 1)),
+    },
+    Command {
+        name: "size",
+        aliases: &[],
+        min_args: 1,
+        max_args: 1,
+        usage: "size <path>",
+        description: "Print the size of a file or directory",
+        handler: |shell, argv| shell.size_command(arg(argv, 1)),
+    },
+    Command {
+        name: "history",
+        aliases: &[],
+        min_args: 0,
+        max_args: 0,
+        usage: "history",
+        description: "List the last commands, run one again with !n or !!",
+        handler: |shell, _| shell.history_command(),
+    },
+    Command {
+        name: "clear",
+        aliases: &["cls"],
+        min_args: 0,
+        max_args: 0,
+        usage: "clear",
+        description: "Clear the screen",
+        handler: |shell, _| shell.clear_command(),
+    },
+    Command {
+        name: "life",
+        aliases: &[],
+        min_args: 0,
+        max_args: 0,
+        usage: "life",
+        description: "Run the game of life, press q or Esc to exit",
+        handler: |shell, _| game_of_life::start(shell.console),
+    },
+    Command {
+        name: "ps",
+        aliases: &[],
+        min_args: 0,
+        max_args: 0,
+        usage: "ps",
+        description: "List the kernel threads with their state and CPU ticks",
+        handler: |_, _| ps_command(),
+    },
+    Command {
+        name: "uptime",
+        aliases: &[],
+        min_args: 0,
+        max_args: 0,
+        usage: "uptime",
+        description: "Print the time since boot",
+        handler: |_, _| uptime_command(),
+    },
+    Command {
+        name: "date",
+        aliases: &[],
+        min_args: 0,
+        max_args: 0,
+        usage: "date",
+        description: "Print the date and time of the real-time clock",
+        handler: |_, _| print!("\n{}", rtc::read()),
+    },
+];
+
+pub fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|command| command.matches(name))
+}
+
+fn ps_command() {
+    print!("\n{:>4}  {:<9} {:>8}  {}", "ID", "STATE", "TICKS", "NAME");
+    for info in thread::threads() {
+        print!("\n{:>4}  {:<9} {:>8}  {}", info.id.as_u64(), info.state, info.ticks, info.name);
+    }
+}
+
+fn uptime_command() {
+    let seconds = time::uptime().as_secs();
+    print!("\nUp {}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+}
+
+fn help_command(name: &str) {
+    if name.is_empty() {
+        println!();
+        for command in COMMANDS {
+            println!("{:<12}{}", command.name, command.description);
+        }
+        print!("Type \"help <command>\" for the usage of a command");
+        return;
+    }
+
+    match find_command(name) {
+        Some(command) => {
+            print!("\nUsage: {}\n{}", command.usage, command.description);
+            if !command.aliases.is_empty() {
+                print!("\nAliases: {}", command.aliases.join(", "));
+            }
+        }
+        None => print!("\n{} Command \"{}\" not found!", ERROR, name),
+    }
+}
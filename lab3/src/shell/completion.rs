@@ -0,0 +1,152 @@
+use super::commands::COMMANDS;
+use crate::fs::{path, InodeId, Vfs};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Candidates for completing the word at the cursor, together with the part of the word they replace.
+pub struct Completion {
+    /// Already typed part of the word that the candidates start with.
+    pub partial: String,
+    /// Full candidates, directories end with `/`.
+    pub candidates: Vec<String>,
+}
+
+impl Completion {
+    /// Text to insert after the partial word: the rest of a unique candidate
+    /// or the longest prefix shared by all candidates.
+    pub fn insertion(&self) -> String {
+        let prefix = common_prefix(&self.candidates);
+        let mut insertion = String::from(prefix.get(self.partial.len()..).unwrap_or(""));
+        // a unique match is finished with a space, unless a directory path may continue
+        if let [candidate] = self.candidates.as_slice() {
+            if !candidate.ends_with('/') {
+                insertion.push(' ');
+            }
+        }
+        insertion
+    }
+}
+
+pub fn complete_command(partial: &str) -> Completion {
+    let mut candidates: Vec<String> = COMMANDS
+        .iter()
+        .flat_map(|command| core::iter::once(&command.name).chain(command.aliases.iter()))
+        .filter(|name| name.starts_with(partial))
+        .map(|name| String::from(*name))
+        .collect();
+    candidates.sort();
+
+    Completion {
+        partial: String::from(partial),
+        candidates,
+    }
+}
+
+/// Completes the last component of `word`, the components before it are resolved from `cwd`.
+pub fn complete_path<V: Vfs + ?Sized>(fs: &V, cwd: InodeId, word: &str) -> Completion {
+    let (dir_path, partial) = match word.rfind('/') {
+        Some(index) => (&word[..index + 1], &word[index + 1..]),
+        None => ("", word),
+    };
+
+    let mut candidates = Vec::new();
+    if let Ok(dir) = path::resolve(fs, cwd, dir_path) {
+        for &child in fs.children(dir).unwrap_or(&[]) {
+            let name = fs.name(child).unwrap_or("");
+            if !name.starts_with(partial) {
+                continue;
+            }
+
+            let mut candidate = String::from(name);
+            if fs.children(child).is_ok() {
+                candidate.push('/');
+            }
+            candidates.push(candidate);
+        }
+    }
+    candidates.sort();
+
+    Completion {
+        partial: String::from(partial),
+        candidates,
+    }
+}
+
+fn common_prefix(candidates: &[String]) -> &str {
+    let first = match candidates.first() {
+        Some(first) => first.as_str(),
+        None => return "",
+    };
+
+    // compare by characters, so the prefix never ends inside a multi-byte character
+    let mut length = first.len();
+    for candidate in &candidates[1..] {
+        length = first
+            .char_indices()
+            .zip(candidate.chars())
+            .find(|((index, a), b)| *index >= length || a != b)
+            .map_or(length.min(candidate.len()).min(first.len()), |((index, _), _)| index);
+    }
+    &first[..length]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::ramfs::RamFs;
+
+    #[test_case]
+    fn completes_unique_command() {
+        let completion = complete_command("make_");
+        assert_eq!(completion.candidates, ["make_dir"]);
+        assert_eq!(completion.insertion(), "dir ");
+    }
+
+    #[test_case]
+    fn ambiguous_command_extends_common_prefix() {
+        let completion = complete_command("c");
+        assert!(completion.candidates.len() > 1);
+        assert!(completion.candidates.iter().any(|candidate| candidate == "cur_dir"));
+        assert_eq!(completion.insertion(), "");
+
+        let completion = complete_command("cu");
+        assert_eq!(completion.insertion(), "r_dir ");
+    }
+
+    #[test_case]
+    fn completes_paths_relative_and_nested() {
+        let mut fs = RamFs::new();
+        let docs = fs.create_dir(fs.root(), "docs").unwrap();
+        fs.create_dir(fs.root(), "downloads").unwrap();
+        fs.create_file(docs, "notes").unwrap();
+
+        let completion = complete_path(&fs, fs.root(), "do");
+        assert_eq!(completion.candidates, ["docs/", "downloads/"]);
+        assert_eq!(completion.insertion(), "");
+
+        let completion = complete_path(&fs, fs.root(), "doc");
+        assert_eq!(completion.insertion(), "s/");
+
+        let completion = complete_path(&fs, docs, "/docs/n");
+        assert_eq!(completion.partial, "n");
+        assert_eq!(completion.insertion(), "otes ");
+
+        let completion = complete_path(&fs, docs, "missing/n");
+        assert!(completion.candidates.is_empty());
+    }
+
+    #[test_case]
+    fn no_candidates_insert_nothing() {
+        let completion = complete_command("xyz");
+        assert!(completion.candidates.is_empty());
+        assert_eq!(completion.insertion(), "");
+    }
+
+    #[test_case]
+    fn common_prefix_keeps_whole_characters() {
+        let candidates = [String::from("caf\u{e9}"), String::from("caf\u{e8}"), String::from("cafe")];
+        assert_eq!(common_prefix(&candidates), "caf");
+        assert_eq!(common_prefix(&candidates[..2]), "caf");
+        assert_eq!(common_prefix(&[String::from("ab"), String::from("abc")]), "ab");
+    }
+}
@@ -0,0 +1,137 @@
+use alloc::collections::VecDeque;
+use alloc::string::String;
+
+pub const HISTORY_SIZE: usize = 32;
+
+/// Bounded list of submitted lines. Entries keep their number after older ones are dropped,
+/// like in bash, so `!n` always refers to what `history` printed.
+pub struct History {
+    entries: VecDeque<String>,
+    first_number: usize,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            entries: VecDeque::with_capacity(HISTORY_SIZE),
+            first_number: 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Adds a line, empty lines and repeats of the last line are skipped.
+    pub fn push(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.back().map(String::as_str) == Some(line) {
+            return;
+        }
+
+        if self.entries.len() == HISTORY_SIZE {
+            self.entries.pop_front();
+            self.first_number += 1;
+        }
+        self.entries.push_back(String::from(line));
+    }
+
+    /// Returns the entry at `index`, where 0 is the oldest stored entry.
+    pub fn at(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Returns the entry with the number printed by `history`.
+    pub fn by_number(&self, number: usize) -> Option<&str> {
+        number.checked_sub(self.first_number).and_then(|index| self.at(index))
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.entries.back().map(String::as_str)
+    }
+
+    /// Iterates over the entries with their numbers, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(move |(index, line)| (self.first_number + index, line.as_str()))
+    }
+
+    /// Replaces a leading `!!` or `!n` with the referenced entry, the rest of the line is kept.
+    /// Returns `Ok(None)` if the line does not start with an event designator.
+    pub fn expand(&self, line: &str) -> Result<Option<String>, String> {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with('!') {
+            return Ok(None);
+        }
+
+        let (designator, rest) = match trimmed.find(char::is_whitespace) {
+            Some(index) => trimmed.split_at(index),
+            None => (trimmed, ""),
+        };
+
+        let entry = match &designator[1..] {
+            "!" => self.last(),
+            number => match number.parse::<usize>() {
+                Ok(number) => self.by_number(number),
+                Err(_) => return Ok(None),
+            },
+        };
+
+        match entry {
+            Some(entry) => {
+                let mut expanded = String::from(entry);
+                expanded.push_str(rest);
+                Ok(Some(expanded))
+            }
+            None => Err(String::from(designator)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test_case]
+    fn push_skips_empty_and_repeated_lines() {
+        let mut history = History::new();
+        history.push("cur_dir");
+        history.push("cur_dir");
+        history.push("  ");
+        history.push("dir_tree");
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.at(0), Some("cur_dir"));
+        assert_eq!(history.last(), Some("dir_tree"));
+    }
+
+    #[test_case]
+    fn oldest_entries_are_dropped_and_numbers_kept() {
+        let mut history = History::new();
+        for i in 1..=HISTORY_SIZE + 2 {
+            history.push(&format!("cmd {}", i));
+        }
+
+        assert_eq!(history.len(), HISTORY_SIZE);
+        assert_eq!(history.by_number(1), None);
+        assert_eq!(history.by_number(3), Some("cmd 3"));
+        assert_eq!(history.iter().next(), Some((3, "cmd 3")));
+        assert_eq!(history.by_number(HISTORY_SIZE + 2).map(String::from), Some(format!("cmd {}", HISTORY_SIZE + 2)));
+    }
+
+    #[test_case]
+    fn expand_event_designators() {
+        let mut history = History::new();
+        history.push("make_dir docs");
+        history.push("cur_dir");
+
+        assert_eq!(history.expand("!!"), Ok(Some(String::from("cur_dir"))));
+        assert_eq!(history.expand("!1"), Ok(Some(String::from("make_dir docs"))));
+        assert_eq!(history.expand("!1 more"), Ok(Some(String::from("make_dir docs more"))));
+        assert_eq!(history.expand("!7"), Err(String::from("!7")));
+        assert_eq!(history.expand("cur_dir"), Ok(None));
+        assert_eq!(history.expand("!abc"), Ok(None));
+    }
+}
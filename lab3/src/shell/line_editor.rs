@@ -0,0 +1,200 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Keeps the input within a few screen rows, so it can always be redrawn in place.
+pub const MAX_INPUT_LENGTH: usize = 256;
+
+/// Input line with a cursor. Editing operations return the index of the first changed byte,
+/// so the caller only has to redraw the line from there.
+pub struct LineEditor {
+    buf: Vec<u8>,
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn new() -> LineEditor {
+        LineEditor {
+            buf: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn text(&self) -> String {
+        self.buf.iter().map(|&byte| byte as char).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the content, the cursor is placed at the end.
+    /// Text beyond `MAX_INPUT_LENGTH` is dropped.
+    pub fn set(&mut self, text: &str) {
+        self.buf.clear();
+        self.buf.extend(text.chars().map(|c| c as u8).take(MAX_INPUT_LENGTH));
+        self.cursor = self.buf.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.cursor = 0;
+    }
+
+    /// Inserts at the cursor, returns `None` if the input is full.
+    pub fn insert(&mut self, byte: u8) -> Option<usize> {
+        if self.buf.len() >= MAX_INPUT_LENGTH {
+            return None;
+        }
+        self.buf.insert(self.cursor, byte);
+        self.cursor += 1;
+        Some(self.cursor - 1)
+    }
+
+    pub fn backspace(&mut self) -> Option<usize> {
+        if self.cursor == 0 {
+            return None;
+        }
+        self.cursor -= 1;
+        self.buf.remove(self.cursor);
+        Some(self.cursor)
+    }
+
+    pub fn delete(&mut self) -> Option<usize> {
+        if self.cursor == self.buf.len() {
+            return None;
+        }
+        self.buf.remove(self.cursor);
+        Some(self.cursor)
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.buf.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.buf.len();
+    }
+
+    /// Removes everything before the cursor (Ctrl+U).
+    pub fn kill_to_start(&mut self) -> Option<usize> {
+        self.kill_range(0, self.cursor)
+    }
+
+    /// Removes everything from the cursor to the end (Ctrl+K).
+    pub fn kill_to_end(&mut self) -> Option<usize> {
+        self.kill_range(self.cursor, self.buf.len())
+    }
+
+    /// Removes the word before the cursor together with the spaces after it (Ctrl+W).
+    pub fn kill_word_back(&mut self) -> Option<usize> {
+        let mut start = self.cursor;
+        while start > 0 && self.buf[start - 1] == b' ' {
+            start -= 1;
+        }
+        while start > 0 && self.buf[start - 1] != b' ' {
+            start -= 1;
+        }
+        self.kill_range(start, self.cursor)
+    }
+
+    fn kill_range(&mut self, start: usize, end: usize) -> Option<usize> {
+        if start == end {
+            return None;
+        }
+        self.buf.drain(start..end);
+        self.cursor = start;
+        Some(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str, cursor: usize) -> LineEditor {
+        let mut editor = LineEditor::new();
+        editor.set(text);
+        editor.cursor = cursor;
+        editor
+    }
+
+    #[test_case]
+    fn insert_in_the_middle() {
+        let mut editor = editor("cd dir", 2);
+
+        assert_eq!(editor.insert(b'x'), Some(2));
+        assert_eq!(editor.text(), "cdx dir");
+        assert_eq!(editor.cursor(), 3);
+    }
+
+    #[test_case]
+    fn backspace_and_delete_at_the_edges() {
+        let mut editor = editor("abc", 0);
+        assert_eq!(editor.backspace(), None);
+        assert_eq!(editor.delete(), Some(0));
+        assert_eq!(editor.text(), "bc");
+
+        editor.move_end();
+        assert_eq!(editor.delete(), None);
+        assert_eq!(editor.backspace(), Some(1));
+        assert_eq!(editor.text(), "b");
+    }
+
+    #[test_case]
+    fn cursor_movement_is_clamped() {
+        let mut editor = editor("ab", 1);
+        editor.move_right();
+        editor.move_right();
+        assert_eq!(editor.cursor(), 2);
+
+        editor.move_home();
+        editor.move_left();
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test_case]
+    fn kill_shortcuts() {
+        let mut editor = editor("write notes  hello", 13);
+        assert_eq!(editor.kill_word_back(), Some(6));
+        assert_eq!(editor.text(), "write hello");
+
+        assert_eq!(editor.kill_to_end(), Some(6));
+        assert_eq!(editor.text(), "write ");
+        assert_eq!(editor.kill_to_end(), None);
+
+        editor.move_left();
+        assert_eq!(editor.kill_to_start(), Some(0));
+        assert_eq!(editor.text(), " ");
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test_case]
+    fn input_is_limited() {
+        let mut editor = LineEditor::new();
+        for _ in 0..MAX_INPUT_LENGTH {
+            assert!(editor.insert(b'a').is_some());
+        }
+        assert_eq!(editor.insert(b'b'), None);
+        assert_eq!(editor.len(), MAX_INPUT_LENGTH);
+
+        editor.set(&"c".repeat(MAX_INPUT_LENGTH + 10));
+        assert_eq!(editor.len(), MAX_INPUT_LENGTH);
+        assert_eq!(editor.cursor(), MAX_INPUT_LENGTH);
+    }
+}
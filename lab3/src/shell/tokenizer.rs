@@ -0,0 +1,122 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub const MAX_TOKEN_LENGTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizeError {
+    UnterminatedQuote,
+    DanglingEscape,
+    TokenTooLong,
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TokenizeError::UnterminatedQuote => f.write_str("Unterminated quote"),
+            TokenizeError::DanglingEscape => f.write_str("Nothing to escape after '\\'"),
+            TokenizeError::TokenTooLong => write!(f, "The maximum length of an argument is {} characters", MAX_TOKEN_LENGTH),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Normal,
+    SingleQuoted,
+    DoubleQuoted,
+}
+
+/// Splits a command line into arguments.
+///
+/// Arguments are separated by whitespace. Inside single quotes everything is literal,
+/// inside double quotes `\"` and `\\` are escapes, outside quotes a backslash escapes any character.
+pub fn tokenize(line: &str) -> Result<Vec<String>, TokenizeError> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    // distinguishes an empty quoted argument ("") from no argument at all
+    let mut in_token = false;
+    let mut state = State::Normal;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (state, c) {
+            (State::Normal, c) if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(core::mem::take(&mut token));
+                    in_token = false;
+                }
+                continue;
+            }
+            (State::Normal, '\'') => state = State::SingleQuoted,
+            (State::Normal, '"') => state = State::DoubleQuoted,
+            (State::Normal, '\\') => token.push(chars.next().ok_or(TokenizeError::DanglingEscape)?),
+            (State::SingleQuoted, '\'') | (State::DoubleQuoted, '"') => state = State::Normal,
+            (State::DoubleQuoted, '\\') => match chars.next() {
+                Some(escaped @ ('"' | '\\')) => token.push(escaped),
+                Some(other) => {
+                    token.push('\\');
+                    token.push(other);
+                }
+                None => return Err(TokenizeError::UnterminatedQuote),
+            },
+            (_, c) => token.push(c),
+        }
+
+        in_token = true;
+        if token.chars().count() > MAX_TOKEN_LENGTH {
+            return Err(TokenizeError::TokenTooLong);
+        }
+    }
+
+    if state != State::Normal {
+        return Err(TokenizeError::UnterminatedQuote);
+    }
+    if in_token {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(line: &str) -> Vec<String> {
+        tokenize(line).unwrap()
+    }
+
+    #[test_case]
+    fn splits_on_whitespace() {
+        assert_eq!(tokens("  write  notes hello\tworld "), ["write", "notes", "hello", "world"]);
+        assert_eq!(tokens(""), Vec::<String>::new());
+        assert_eq!(tokens("   "), Vec::<String>::new());
+    }
+
+    #[test_case]
+    fn quotes_group_words() {
+        assert_eq!(tokens("write notes \"hello world\""), ["write", "notes", "hello world"]);
+        assert_eq!(tokens("write 'a \"b\" c'"), ["write", "a \"b\" c"]);
+        assert_eq!(tokens("make_dir my\" \"dir"), ["make_dir", "my dir"]);
+        assert_eq!(tokens("touch \"\" ''"), ["touch", "", ""]);
+    }
+
+    #[test_case]
+    fn backslash_escapes() {
+        assert_eq!(tokens("make_dir my\\ dir"), ["make_dir", "my dir"]);
+        assert_eq!(tokens("write \"say \\\"hi\\\" \\\\ \\n\""), ["write", "say \"hi\" \\ \\n"]);
+        assert_eq!(tokens("write 'no\\escape'"), ["write", "no\\escape"]);
+    }
+
+    #[test_case]
+    fn errors() {
+        assert_eq!(tokenize("write \"open"), Err(TokenizeError::UnterminatedQuote));
+        assert_eq!(tokenize("write 'open"), Err(TokenizeError::UnterminatedQuote));
+        assert_eq!(tokenize("write end\\"), Err(TokenizeError::DanglingEscape));
+
+        let long_token = "x".repeat(MAX_TOKEN_LENGTH + 1);
+        assert_eq!(tokenize(&long_token), Err(TokenizeError::TokenTooLong));
+        assert_eq!(tokenize(&long_token[1..]).map(|tokens| tokens.len()), Ok(1));
+    }
+}
@@ -0,0 +1,81 @@
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use crate::rtc;
+use crate::time::{self, TIMER_FREQUENCY};
+use crate::vga_buf::{self, BUF_WIDTH, CONSOLES};
+
+/// Redraw twice a second, so the clock is never more than half a second behind the RTC.
+const UPDATE_TICKS: u64 = TIMER_FREQUENCY / 2;
+// the date takes 19 columns, one is left free at the right edge
+const DATE_COLUMN: usize = BUF_WIDTH as usize - 20;
+
+static NEXT_UPDATE: AtomicU64 = AtomicU64::new(0);
+// the console number shown, a switch redraws right away
+static SHOWN_CONSOLE: AtomicUsize = AtomicUsize::new(0);
+
+/// Formats into a fixed line, the timer interrupt must not allocate.
+struct Line {
+    bytes: [u8; BUF_WIDTH as usize],
+    len: usize,
+}
+
+impl Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if self.len < self.bytes.len() {
+                self.bytes[self.len] = byte;
+                self.len += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn format(console: usize, date: rtc::DateTime, uptime_seconds: u64) -> Line {
+    let mut line = Line { bytes: [b' '; BUF_WIDTH as usize], len: 0 };
+    let _ = write!(
+        line,
+        " unios | tty{} | up {}:{:02}:{:02}",
+        console + 1,
+        uptime_seconds / 3600,
+        uptime_seconds / 60 % 60,
+        uptime_seconds % 60
+    );
+    line.len = line.len.max(DATE_COLUMN);
+    let _ = write!(line, "{}", date);
+    line
+}
+
+/// Called from the timer interrupt, redraws the status bar every `UPDATE_TICKS` ticks
+/// and after a console switch.
+pub fn on_timer_tick() {
+    let now = time::ticks();
+    let console = vga_buf::active_console();
+    if now < NEXT_UPDATE.load(Ordering::Relaxed) && console == SHOWN_CONSOLE.load(Ordering::Relaxed) {
+        return;
+    }
+    let date = match rtc::try_read() {
+        Some(date) => date,
+        None => return,
+    };
+    // the interrupted code may hold the screen, try again on the next tick then
+    if let Some(mut screen) = CONSOLES[console].try_lock() {
+        screen.write_status(&format(console, date, time::uptime().as_secs()).bytes);
+        NEXT_UPDATE.store(now + UPDATE_TICKS, Ordering::Relaxed);
+        SHOWN_CONSOLE.store(console, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn formats_uptime_and_date() {
+        let date = rtc::DateTime { year: 2026, month: 10, day: 18, hour: 9, minute: 5, second: 7 };
+        let line = format(1, date, 3723);
+
+        assert!(line.bytes.starts_with(b" unios | tty2 | up 1:02:03 "));
+        assert_eq!(&line.bytes[DATE_COLUMN..], b"2026-10-18 09:05:07 ");
+    }
+}
@@ -0,0 +1,121 @@
+use super::{Task, TaskId};
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::task::{Context, Poll, Waker};
+use crossbeam_queue::ArrayQueue;
+use x86_64::instructions::interrupts;
+
+const TASK_QUEUE_SIZE: usize = 100;
+
+/// Cooperative executor, a task is only polled again after its waker was called.
+pub struct Executor {
+    tasks: BTreeMap<TaskId, Task>,
+    // ids of the tasks to poll, wakers push to it, possibly from an interrupt handler
+    task_queue: Arc<ArrayQueue<TaskId>>,
+    waker_cache: BTreeMap<TaskId, Waker>,
+}
+
+impl Executor {
+    pub fn new() -> Self {
+        Executor {
+            tasks: BTreeMap::new(),
+            task_queue: Arc::new(ArrayQueue::new(TASK_QUEUE_SIZE)),
+            waker_cache: BTreeMap::new(),
+        }
+    }
+
+    pub fn spawn(&mut self, task: Task) {
+        let task_id = task.id;
+        if self.tasks.insert(task.id, task).is_some() {
+            panic!("task with same ID already in tasks");
+        }
+        self.task_queue.push(task_id).expect("task queue full");
+    }
+
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.run_ready_tasks();
+            self.sleep_if_idle();
+        }
+    }
+
+    fn run_ready_tasks(&mut self) {
+        while let Some(task_id) = self.task_queue.pop() {
+            let task = match self.tasks.get_mut(&task_id) {
+                Some(task) => task,
+                // the task has already finished
+                None => continue,
+            };
+            let waker = self
+                .waker_cache
+                .entry(task_id)
+                .or_insert_with(|| TaskWaker::new(task_id, self.task_queue.clone()));
+            let mut context = Context::from_waker(waker);
+            if let Poll::Ready(()) = task.poll(&mut context) {
+                self.tasks.remove(&task_id);
+                self.waker_cache.remove(&task_id);
+            }
+        }
+    }
+
+    fn sleep_if_idle(&self) {
+        // checking with interrupts disabled means a wakeup arriving in between still ends the `hlt`
+        interrupts::disable();
+        if self.task_queue.is_empty() {
+            interrupts::enable_and_hlt();
+        } else {
+            interrupts::enable();
+        }
+    }
+}
+
+struct TaskWaker {
+    task_id: TaskId,
+    task_queue: Arc<ArrayQueue<TaskId>>,
+}
+
+impl TaskWaker {
+    fn new(task_id: TaskId, task_queue: Arc<ArrayQueue<TaskId>>) -> Waker {
+        Waker::from(Arc::new(TaskWaker { task_id, task_queue }))
+    }
+
+    fn wake_task(&self) {
+        // wakers may run in an interrupt handler, so never panic here;
+        // the queue has room for far more wakeups than there are tasks
+        let _ = self.task_queue.push(self.task_id);
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_task();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wake_task();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    #[test_case]
+    fn runs_spawned_tasks_to_completion() {
+        static POLLS: AtomicU32 = AtomicU32::new(0);
+        let mut executor = Executor::new();
+        executor.spawn(Task::new(async {
+            POLLS.fetch_add(1, Ordering::Relaxed);
+        }));
+        executor.spawn(Task::new(async {
+            POLLS.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        executor.run_ready_tasks();
+        assert_eq!(POLLS.load(Ordering::Relaxed), 2);
+        assert!(executor.tasks.is_empty());
+        assert!(executor.waker_cache.is_empty());
+    }
+}
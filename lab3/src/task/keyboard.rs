@@ -0,0 +1,182 @@
+use crate::ring_buffer::RingBuffer;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll};
+use futures_util::future;
+use futures_util::stream::{self, Stream, StreamExt};
+use futures_util::task::AtomicWaker;
+use pc_keyboard::{layouts, DecodedKey, HandleControl, KeyCode, KeyEvent, KeyState, Keyboard, ScancodeSet1};
+
+const INPUT_QUEUE_SIZE: usize = 128;
+
+// filled by the interrupt handlers, drained by the streams below
+static SCANCODES: RingBuffer<INPUT_QUEUE_SIZE> = RingBuffer::new();
+static SCANCODE_WAKER: AtomicWaker = AtomicWaker::new();
+static SERIAL_INPUT: RingBuffer<INPUT_QUEUE_SIZE> = RingBuffer::new();
+static SERIAL_WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Called by the keyboard interrupt handler, must not block or allocate.
+pub(crate) fn add_scancode(scancode: u8) {
+    // if the queue is full the key is lost
+    if SCANCODES.push(scancode) {
+        SCANCODE_WAKER.wake();
+    }
+}
+
+/// Called by the serial interrupt handler for every received byte.
+pub(crate) fn add_serial_byte(byte: u8) {
+    if SERIAL_INPUT.push(byte) {
+        SERIAL_WAKER.wake();
+    }
+}
+
+/// Polls `queue`, registering the waker before the second check so a byte pushed in between is not missed.
+fn poll_queue(queue: &RingBuffer<INPUT_QUEUE_SIZE>, waker: &AtomicWaker, cx: &mut Context) -> Poll<Option<u8>> {
+    if let Some(byte) = queue.pop() {
+        return Poll::Ready(Some(byte));
+    }
+
+    waker.register(cx.waker());
+    match queue.pop() {
+        Some(byte) => {
+            waker.take();
+            Poll::Ready(Some(byte))
+        }
+        None => Poll::Pending,
+    }
+}
+
+/// Scancodes from the PS/2 keyboard, the stream never ends.
+pub struct ScancodeStream {
+    _private: (),
+}
+
+impl ScancodeStream {
+    /// The queue has a single consumer, so there may only be one stream.
+    pub fn new() -> Self {
+        static CREATED: AtomicBool = AtomicBool::new(false);
+        if CREATED.swap(true, Ordering::Relaxed) {
+            panic!("ScancodeStream::new should only be called once");
+        }
+        ScancodeStream { _private: () }
+    }
+}
+
+impl Stream for ScancodeStream {
+    type Item = u8;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u8>> {
+        poll_queue(&SCANCODES, &SCANCODE_WAKER, cx)
+    }
+}
+
+/// Bytes received on COM1, the stream never ends.
+pub struct SerialStream {
+    _private: (),
+}
+
+impl SerialStream {
+    /// The queue has a single consumer, so there may only be one stream.
+    pub fn new() -> Self {
+        static CREATED: AtomicBool = AtomicBool::new(false);
+        if CREATED.swap(true, Ordering::Relaxed) {
+            panic!("SerialStream::new should only be called once");
+        }
+        SerialStream { _private: () }
+    }
+}
+
+impl Stream for SerialStream {
+    type Item = u8;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u8>> {
+        poll_queue(&SERIAL_INPUT, &SERIAL_WAKER, cx)
+    }
+}
+
+/// Modifier keys held down when a key was pressed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl Modifiers {
+    /// Tracks the modifier keys, the decoder keeps its own state but does not expose it.
+    fn update(&mut self, event: &KeyEvent) {
+        let down = event.state == KeyState::Down;
+        match event.code {
+            KeyCode::ShiftLeft | KeyCode::ShiftRight => self.shift = down,
+            KeyCode::ControlLeft | KeyCode::ControlRight => self.ctrl = down,
+            KeyCode::AltLeft | KeyCode::AltRight => self.alt = down,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub key: DecodedKey,
+    pub modifiers: Modifiers,
+}
+
+fn decode_serial_byte(byte: u8) -> DecodedKey {
+    match byte {
+        // terminals send CR on Enter and DEL on Backspace
+        b'\r' => DecodedKey::Unicode('\n'),
+        0x7F => DecodedKey::Unicode(8 as char),
+        b => DecodedKey::Unicode(b as char),
+    }
+}
+
+/// Keys typed on the keyboard or received over serial, as one stream.
+/// Serial input carries no modifiers, terminals send them as part of the character.
+pub fn keys() -> impl Stream<Item = KeyPress> {
+    let mut keyboard = Keyboard::new(
+        layouts::Us104Key,
+        ScancodeSet1,
+        // Ctrl+letter is delivered as a control character, the shell binds some of them
+        HandleControl::MapLettersToUnicode,
+    );
+    let mut modifiers = Modifiers::default();
+    let keyboard_keys = ScancodeStream::new().filter_map(move |scancode| {
+        let key = match keyboard.add_byte(scancode) {
+            Ok(Some(key_event)) => {
+                modifiers.update(&key_event);
+                keyboard.process_keyevent(key_event).map(|key| KeyPress { key, modifiers })
+            }
+            _ => None,
+        };
+        future::ready(key)
+    });
+    let serial_keys = SerialStream::new().map(|byte| KeyPress {
+        key: decode_serial_byte(byte),
+        modifiers: Modifiers::default(),
+    });
+    stream::select(keyboard_keys, serial_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn serial_bytes_map_to_keys() {
+        assert_eq!(decode_serial_byte(b'\r'), DecodedKey::Unicode('\n'));
+        assert_eq!(decode_serial_byte(0x7F), DecodedKey::Unicode(8 as char));
+        assert_eq!(decode_serial_byte(b'a'), DecodedKey::Unicode('a'));
+    }
+
+    #[test_case]
+    fn tracks_modifier_keys() {
+        let mut modifiers = Modifiers::default();
+        modifiers.update(&KeyEvent { code: KeyCode::ShiftLeft, state: KeyState::Down });
+        modifiers.update(&KeyEvent { code: KeyCode::AltRight, state: KeyState::Down });
+        modifiers.update(&KeyEvent { code: KeyCode::A, state: KeyState::Down });
+        assert_eq!(modifiers, Modifiers { shift: true, ctrl: false, alt: true });
+
+        modifiers.update(&KeyEvent { code: KeyCode::ShiftLeft, state: KeyState::Up });
+        assert!(!modifiers.shift);
+    }
+}
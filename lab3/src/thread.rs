@@ -0,0 +1,377 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::arch::global_asm;
+use core::fmt;
+use spin::{Mutex, MutexGuard};
+use x86_64::instructions::interrupts;
+use crate::time;
+
+/// Threads have no guard page, a thread that overflows its stack corrupts the heap.
+const STACK_SIZE: usize = 4096 * 4;
+const MAX_THREADS: usize = 16;
+
+static SCHEDULER: Mutex<Scheduler> = Mutex::new(Scheduler::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadId(u64);
+
+impl ThreadId {
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+    Running,
+    Ready,
+    /// Sleeping until `time::ticks` reaches the value.
+    Sleeping(u64),
+    Finished,
+}
+
+impl fmt::Display for ThreadState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ThreadState::Running => "running",
+            ThreadState::Ready => "ready",
+            ThreadState::Sleeping(_) => "sleeping",
+            ThreadState::Finished => "finished",
+        };
+        // `pad` so column widths apply
+        f.pad(name)
+    }
+}
+
+/// What `threads` reports about a thread.
+#[derive(Debug, Clone, Copy)]
+pub struct ThreadInfo {
+    pub id: ThreadId,
+    pub name: &'static str,
+    pub state: ThreadState,
+    /// Timer ticks the thread was running for.
+    pub ticks: u64,
+}
+
+struct Thread {
+    info: ThreadInfo,
+    // not used for the boot thread, it is already running
+    entry: fn(),
+    // saved stack pointer while the thread is switched out
+    rsp: u64,
+    // the boot thread runs on the stack the bootloader gave it
+    _stack: Option<Vec<u8>>,
+}
+
+impl Thread {
+    fn new(id: u64, name: &'static str, entry: fn(), stack: Option<Vec<u8>>) -> Thread {
+        Thread {
+            info: ThreadInfo { id: ThreadId(id), name, state: ThreadState::Ready, ticks: 0 },
+            entry,
+            rsp: 0,
+            _stack: stack,
+        }
+    }
+
+    /// Allocates a stack prepared so that the first switch to it "returns" into `thread_start`.
+    fn with_stack(id: u64, name: &'static str, entry: fn()) -> Box<Thread> {
+        let mut stack = vec![0u8; STACK_SIZE];
+        let top = (stack.as_mut_ptr() as u64 + STACK_SIZE as u64) & !0xF;
+        // from the top: a null return address for `thread_start`, the address `switch_context`
+        // returns to, then the six callee-saved registers it pops
+        let rsp = top - 8 * 8;
+        unsafe {
+            let frame = rsp as *mut u64;
+            for i in 0..6 {
+                frame.add(i).write(0);
+            }
+            frame.add(6).write(thread_start as *const () as u64);
+            frame.add(7).write(0);
+        }
+
+        let mut thread = Box::new(Thread::new(id, name, entry, Some(stack)));
+        thread.rsp = rsp;
+        thread
+    }
+}
+
+struct Scheduler {
+    threads: [Option<Box<Thread>>; MAX_THREADS],
+    current: usize,
+    idle: usize,
+    next_id: u64,
+}
+
+impl Scheduler {
+    const fn new() -> Scheduler {
+        Scheduler {
+            threads: [const { None }; MAX_THREADS],
+            current: 0,
+            idle: 0,
+            next_id: 0,
+        }
+    }
+
+    fn is_initialized(&self) -> bool {
+        self.threads[self.current].is_some()
+    }
+
+    fn current(&mut self) -> &mut Thread {
+        self.threads[self.current].as_mut().expect("no current thread")
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn free_slot(&self) -> Option<usize> {
+        self.threads.iter().position(|slot| slot.is_none())
+    }
+
+    fn wake_sleeping(&mut self) {
+        let now = time::ticks();
+        for thread in self.threads.iter_mut().flatten() {
+            if let ThreadState::Sleeping(until) = thread.info.state {
+                if until <= now {
+                    thread.info.state = ThreadState::Ready;
+                }
+            }
+        }
+    }
+
+    /// Picks the next ready thread round-robin, the idle thread only runs if no other can.
+    /// Returns where to save the current stack pointer and the stack pointer to switch to.
+    fn next_switch(&mut self) -> Option<(*mut u64, u64)> {
+        let ready = |slot: &Option<Box<Thread>>| {
+            matches!(slot, Some(thread) if thread.info.state == ThreadState::Ready)
+        };
+        let next = (1..=MAX_THREADS)
+            .map(|i| (self.current + i) % MAX_THREADS)
+            .find(|&index| index != self.idle && ready(&self.threads[index]));
+
+        let next = match next {
+            Some(index) => index,
+            None if self.current().info.state == ThreadState::Running => return None,
+            None => self.idle,
+        };
+        if next == self.current {
+            self.current().info.state = ThreadState::Running;
+            return None;
+        }
+
+        let old = self.current();
+        if old.info.state == ThreadState::Running {
+            old.info.state = ThreadState::Ready;
+        }
+        let old_rsp = &mut old.rsp as *mut u64;
+
+        self.current = next;
+        let new = self.current();
+        new.info.state = ThreadState::Running;
+        Some((old_rsp, new.rsp))
+    }
+}
+
+extern "C" {
+    /// Saves the callee-saved registers and the stack pointer to `*old_rsp`, then restores them
+    /// from `new_rsp` and returns on the other thread's stack.
+    fn switch_context(old_rsp: *mut u64, new_rsp: u64);
+}
+
+global_asm!(
+    ".global switch_context",
+    "switch_context:",
+    "push rbp",
+    "push rbx",
+    "push r12",
+    "push r13",
+    "push r14",
+    "push r15",
+    "mov [rdi], rsp",
+    "mov rsp, rsi",
+    "pop r15",
+    "pop r14",
+    "pop r13",
+    "pop r12",
+    "pop rbx",
+    "pop rbp",
+    "ret",
+);
+
+/// Switches with interrupts disabled and the scheduler unlocked; the other thread
+/// re-enables interrupts when it returns from its interrupt handler or `without_interrupts`.
+fn switch(mut scheduler: MutexGuard<'_, Scheduler>) {
+    let switch = scheduler.next_switch();
+    drop(scheduler);
+    if let Some((old_rsp, new_rsp)) = switch {
+        unsafe { switch_context(old_rsp, new_rsp) };
+    }
+}
+
+/// First code a spawned thread runs, interrupts are still disabled from the switch.
+extern "C" fn thread_start() -> ! {
+    let entry = SCHEDULER.lock().current().entry;
+    interrupts::enable();
+    entry();
+    exit();
+}
+
+fn idle_loop() {
+    loop {
+        x86_64::instructions::hlt();
+    }
+}
+
+/// Registers the running code as the "kernel" thread and starts the idle thread.
+/// Must be called after the heap is set up and before timer interrupts are enabled.
+pub fn init() {
+    let mut scheduler = SCHEDULER.lock();
+
+    let id = scheduler.next_id();
+    let mut kernel = Thread::new(id, "kernel", idle_loop, None);
+    kernel.info.state = ThreadState::Running;
+    scheduler.threads[0] = Some(Box::new(kernel));
+    scheduler.current = 0;
+
+    let id = scheduler.next_id();
+    scheduler.threads[1] = Some(Thread::with_stack(id, "idle", idle_loop));
+    scheduler.idle = 1;
+}
+
+/// Starts `entry` in a new thread, returns `None` if the thread table is full.
+pub fn spawn(name: &'static str, entry: fn()) -> Option<ThreadId> {
+    // allocate and free outside of the lock with interrupts enabled: a preempted thread may hold
+    // the heap lock and would never get to release it
+    let mut finished: [Option<Box<Thread>>; MAX_THREADS] = [const { None }; MAX_THREADS];
+    let mut thread = Some(Thread::with_stack(0, name, entry));
+
+    let id = interrupts::without_interrupts(|| {
+        let mut scheduler = SCHEDULER.lock();
+        for (slot, finished) in scheduler.threads.iter_mut().zip(finished.iter_mut()) {
+            if matches!(slot, Some(thread) if thread.info.state == ThreadState::Finished) {
+                *finished = slot.take();
+            }
+        }
+
+        let slot = scheduler.free_slot()?;
+        let id = scheduler.next_id();
+        let mut new = thread.take()?;
+        new.info.id = ThreadId(id);
+        scheduler.threads[slot] = Some(new);
+        Some(ThreadId(id))
+    });
+    drop(finished);
+    drop(thread);
+    id
+}
+
+/// Gives the rest of the time slice to the next ready thread.
+pub fn yield_now() {
+    interrupts::without_interrupts(|| switch(SCHEDULER.lock()));
+}
+
+/// Blocks the current thread for at least `ticks` timer ticks.
+pub fn sleep(ticks: u64) {
+    if ticks == 0 {
+        return yield_now();
+    }
+    interrupts::without_interrupts(|| {
+        let mut scheduler = SCHEDULER.lock();
+        let until = time::ticks() + ticks;
+        scheduler.current().info.state = ThreadState::Sleeping(until);
+        switch(scheduler);
+    });
+}
+
+/// Ends the current thread, its stack is freed by a later `spawn`.
+pub fn exit() -> ! {
+    interrupts::disable();
+    let mut scheduler = SCHEDULER.lock();
+    scheduler.current().info.state = ThreadState::Finished;
+    switch(scheduler);
+    unreachable!("finished thread was scheduled again");
+}
+
+/// Called from the timer interrupt after the end of interrupt was sent, switches to the next thread.
+pub fn on_timer_tick() {
+    let mut scheduler = SCHEDULER.lock();
+    if !scheduler.is_initialized() {
+        return;
+    }
+    scheduler.current().info.ticks += 1;
+    scheduler.wake_sleeping();
+    switch(scheduler);
+}
+
+/// The threads that have not finished yet.
+pub fn threads() -> Vec<ThreadInfo> {
+    let mut infos = [None; MAX_THREADS];
+    interrupts::without_interrupts(|| {
+        let scheduler = SCHEDULER.lock();
+        for (info, slot) in infos.iter_mut().zip(scheduler.threads.iter()) {
+            *info = slot.as_ref().map(|thread| thread.info);
+        }
+    });
+    infos
+        .iter()
+        .flatten()
+        .filter(|info| info.state != ThreadState::Finished)
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+    static RUNS: AtomicU32 = AtomicU32::new(0);
+
+    fn count_and_exit() {
+        RUNS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test_case]
+    fn spawned_thread_runs_and_finishes() {
+        let id = spawn("test", count_and_exit).expect("thread table full");
+        let before = RUNS.load(Ordering::SeqCst);
+        for _ in 0..100 {
+            if RUNS.load(Ordering::SeqCst) > before {
+                break;
+            }
+            yield_now();
+        }
+        // it may have run before `before` was read
+        assert!(RUNS.load(Ordering::SeqCst) >= 1);
+        assert!(threads().iter().all(|info| info.id != id));
+    }
+
+    static WOKE: AtomicBool = AtomicBool::new(false);
+
+    fn sleep_then_flag() {
+        sleep(2);
+        WOKE.store(true, Ordering::SeqCst);
+    }
+
+    #[test_case]
+    fn sleeping_thread_is_woken_by_the_timer() {
+        let id = spawn("sleeper", sleep_then_flag).expect("thread table full");
+        yield_now();
+        let sleeping = threads().into_iter().find(|info| info.id == id);
+        assert!(matches!(sleeping.map(|info| info.state), Some(ThreadState::Sleeping(_)) | None));
+
+        while !WOKE.load(Ordering::SeqCst) {
+            x86_64::instructions::hlt();
+        }
+    }
+
+    #[test_case]
+    fn kernel_thread_is_listed() {
+        let threads = threads();
+        let kernel = threads.iter().find(|info| info.name == "kernel").expect("no kernel thread");
+        assert_eq!(kernel.state, ThreadState::Running);
+        assert!(threads.iter().any(|info| info.name == "idle"));
+    }
+}
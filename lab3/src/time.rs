@@ -0,0 +1,74 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use x86_64::instructions::port::Port;
+use crate::thread;
+
+/// Input clock of the programmable interval timer, in Hz.
+const PIT_FREQUENCY: u64 = 1_193_182;
+/// Frequency of the timer interrupt, instead of the BIOS default of ~18.2 Hz.
+pub const TIMER_FREQUENCY: u64 = 100;
+
+const PIT_CHANNEL_0: u16 = 0x40;
+const PIT_COMMAND: u16 = 0x43;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Programs PIT channel 0 to raise IRQ 0 `TIMER_FREQUENCY` times a second.
+pub fn init() {
+    let divisor = (PIT_FREQUENCY / TIMER_FREQUENCY) as u16;
+    let mut command: Port<u8> = Port::new(PIT_COMMAND);
+    let mut channel_0: Port<u8> = Port::new(PIT_CHANNEL_0);
+    unsafe {
+        // channel 0, low byte then high byte, mode 2 (rate generator), binary
+        command.write(0b0011_0100);
+        channel_0.write(divisor as u8);
+        channel_0.write((divisor >> 8) as u8);
+    }
+}
+
+/// Called from the timer interrupt.
+pub fn on_timer_tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Timer interrupts since boot.
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+pub fn uptime() -> Duration {
+    let ticks = ticks();
+    Duration::from_secs(ticks / TIMER_FREQUENCY)
+        + Duration::from_nanos((ticks % TIMER_FREQUENCY) * 1_000_000_000 / TIMER_FREQUENCY)
+}
+
+/// Rounds up, so a sleep is never shorter than asked for.
+pub fn ms_to_ticks(ms: u64) -> u64 {
+    (ms * TIMER_FREQUENCY).div_ceil(1000)
+}
+
+/// Blocks the current thread for at least `ms` milliseconds.
+pub fn sleep_ms(ms: u64) {
+    thread::sleep(ms_to_ticks(ms));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn ms_round_up_to_ticks() {
+        assert_eq!(ms_to_ticks(0), 0);
+        assert_eq!(ms_to_ticks(1), 1);
+        assert_eq!(ms_to_ticks(1000), TIMER_FREQUENCY);
+        assert_eq!(ms_to_ticks(1001), TIMER_FREQUENCY + 1);
+    }
+
+    #[test_case]
+    fn sleep_ms_waits_for_the_timer() {
+        let start = ticks();
+        sleep_ms(30);
+        assert!(ticks() >= start + ms_to_ticks(30));
+        assert!(uptime() >= Duration::from_millis(30));
+    }
+}
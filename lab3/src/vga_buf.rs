@@ -1,7 +1,16 @@
 use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_static::lazy_static;
 use spin::Mutex;
+use crate::serial::SERIAL1;
 use x86_64::instructions::{interrupts::without_interrupts, port::{Port, PortGeneric, ReadWriteAccess}};
+use ansi::{Action, Params, Parser};
+use scrollback::{Scrollback, ROW_BYTES, SCROLLBACK_LINES};
+pub use region::Region;
+
+mod ansi;
+mod region;
+mod scrollback;
 
 #[macro_export]
 macro_rules! print {
@@ -18,27 +27,156 @@ macro_rules! println {
 pub fn _print(args: fmt::Arguments) {
     use core::fmt::Write;
     without_interrupts(|| {
-        SCREEN.lock().write_fmt(args).unwrap();
+        // mirror to COM1 first, so the output (e.g. a panic message) is not lost
+        // even if the screen is locked or has scrolled away
+        SERIAL1.lock().write_fmt(args).unwrap();
+        CONSOLES[active_console()].lock().write_fmt(args).unwrap();
     });
 }
 
-const BUF_HEIGHT: u32 = 25;
-const BUF_WIDTH: u32 = 80;
+pub const BUF_HEIGHT: u32 = 25;
+pub const BUF_WIDTH: u32 = 80;
+/// Rows available for text, the last row is reserved for the status bar.
+pub const TEXT_HEIGHT: u32 = BUF_HEIGHT - 1;
+const STATUS_ROW: u32 = BUF_HEIGHT - 1;
+const STATUS_COLOR: ColorCode = ColorCode::new(Color::Black, Color::LightGray);
+const DEFAULT_FOREGROUND: Color = Color::LightGreen;
+const DEFAULT_BACKGROUND: Color = Color::Black;
+pub const DEFAULT_COLOR: ColorCode = ColorCode::new(DEFAULT_FOREGROUND, DEFAULT_BACKGROUND);
+const TAB_WIDTH: usize = 8;
+const DEFAULT_TAB_STOPS: [bool; BUF_WIDTH as usize] = {
+    let mut stops = [false; BUF_WIDTH as usize];
+    let mut col = TAB_WIDTH;
+    while col < BUF_WIDTH as usize {
+        stops[col] = true;
+        col += TAB_WIDTH;
+    }
+    stops
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Color {
+    Black = 0,
+    Blue = 1,
+    Green = 2,
+    Cyan = 3,
+    Red = 4,
+    Magenta = 5,
+    Brown = 6,
+    LightGray = 7,
+    DarkGray = 8,
+    LightBlue = 9,
+    LightGreen = 10,
+    LightCyan = 11,
+    LightRed = 12,
+    Pink = 13,
+    Yellow = 14,
+    White = 15,
+}
+
+// ANSI color numbers 0..=7, and their bright variants
+const ANSI_COLORS: [Color; 8] = [
+    Color::Black, Color::Red, Color::Green, Color::Brown,
+    Color::Blue, Color::Magenta, Color::Cyan, Color::LightGray,
+];
+const ANSI_BRIGHT_COLORS: [Color; 8] = [
+    Color::DarkGray, Color::LightRed, Color::LightGreen, Color::Yellow,
+    Color::LightBlue, Color::Pink, Color::LightCyan, Color::White,
+];
+
+/// VGA attribute byte, the foreground in the low and the background in the high nibble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ColorCode(u8);
+
+impl ColorCode {
+    pub const fn new(foreground: Color, background: Color) -> ColorCode {
+        ColorCode((background as u8) << 4 | (foreground as u8))
+    }
+
+    pub fn with_foreground(self, foreground: Color) -> ColorCode {
+        ColorCode(self.0 & 0xF0 | foreground as u8)
+    }
+
+    pub fn with_background(self, background: Color) -> ColorCode {
+        ColorCode(self.0 & 0x0F | (background as u8) << 4)
+    }
+
+    /// Sets or clears the intensity bit of the foreground, e.g. Red and LightRed.
+    fn with_bright_foreground(self, bright: bool) -> ColorCode {
+        if bright {
+            ColorCode(self.0 | 0x08)
+        } else {
+            ColorCode(self.0 & !0x08)
+        }
+    }
+
+    pub fn as_u8(self) -> u8 {
+        self.0
+    }
+}
 const BUF_SIZE: usize = (BUF_HEIGHT * BUF_WIDTH * 2) as usize;
 
+/// Consoles switched with Alt+F1 and onwards, each one has its own screen and shell.
+pub const CONSOLE_COUNT: usize = 4;
+
+// the text of the consoles that are not shown, the first console starts out in video memory
+static mut BACK_BUFFERS: [[u8; BUF_SIZE]; CONSOLE_COUNT - 1] = [[0; BUF_SIZE]; CONSOLE_COUNT - 1];
+static ACTIVE_CONSOLE: AtomicUsize = AtomicUsize::new(0);
+
 lazy_static! {
-    pub static ref SCREEN: Mutex<Screen> = Mutex::new(
-        {
-            let mut screen = Screen {
-                color: 0xa,
-                buffer: unsafe {&mut *(0xb8000 as *mut [u8; BUF_SIZE])},
-                line: 0,
-                col: 0
-            };
-            screen.clear();
-            screen
-        }
-    );
+    pub static ref CONSOLES: [Mutex<Screen>; CONSOLE_COUNT] = core::array::from_fn(|index| {
+        let buffer = match index {
+            0 => unsafe { &mut *(0xb8000 as *mut [u8; BUF_SIZE]) },
+            // each back buffer is handed out once, when the consoles are created
+            _ => unsafe { &mut *core::ptr::addr_of_mut!(BACK_BUFFERS[index - 1]) },
+        };
+        let mut screen = Screen {
+            color: DEFAULT_COLOR,
+            buffer,
+            active: index == 0,
+            text_area: Region::new(0, 0, TEXT_HEIGHT, BUF_WIDTH),
+            line: 0,
+            col: 0,
+            parser: Parser::new(),
+            saved: (0, 0, DEFAULT_COLOR),
+            tab_stops: DEFAULT_TAB_STOPS,
+            scrollback: None,
+            view_offset: 0,
+        };
+        screen.clear();
+        Mutex::new(screen)
+    });
+}
+
+/// The console shown on the screen, `print!` writes to it.
+pub fn active_console() -> usize {
+    ACTIVE_CONSOLE.load(Ordering::Relaxed)
+}
+
+/// Shows console `index`: the text rows of the shown console are swapped with its back buffer,
+/// the status bar stays in video memory.
+pub fn switch_console(index: usize) {
+    let current = active_console();
+    if index == current || index >= CONSOLE_COUNT {
+        return;
+    }
+    without_interrupts(|| {
+        // lock in index order, so two switches can not wait on each other
+        let (first, second) = (current.min(index), current.max(index));
+        let mut first = CONSOLES[first].lock();
+        let mut second = CONSOLES[second].lock();
+        let (old, new) = if current < index { (&mut *first, &mut *second) } else { (&mut *second, &mut *first) };
+
+        let text = TEXT_HEIGHT as usize * ROW_BYTES;
+        old.buffer[..text].swap_with_slice(&mut new.buffer[..text]);
+        core::mem::swap(&mut old.buffer, &mut new.buffer);
+        old.active = false;
+        new.active = true;
+        new.show_cursor();
+        ACTIVE_CONSOLE.store(index, Ordering::Relaxed);
+    });
 }
 
 pub struct AsciiChar {
@@ -47,10 +185,31 @@ pub struct AsciiChar {
 }
 
 pub struct Screen {
-    color: u8,
+    color: ColorCode,
+    /// Video memory while the console is shown, a back buffer otherwise.
     pub buffer: &'static mut [u8; BUF_SIZE],
+    // only the shown console moves the hardware cursor
+    active: bool,
+    // the part of the screen text is printed to, the cursor is relative to its top left corner
+    text_area: Region,
     line: u32,
-    col: u32
+    col: u32,
+    parser: Parser,
+    // line, column and color stored by `ESC 7` / `CSI s`
+    saved: (u32, u32, ColorCode),
+    tab_stops: [bool; BUF_WIDTH as usize],
+    // allocated by `init_scrollback` once there is a heap
+    scrollback: Option<Scrollback>,
+    // how many lines the view is scrolled back, 0 shows the live screen
+    view_offset: usize,
+}
+
+/// Starts keeping the lines that scroll off the screen of every console, needs the heap.
+pub fn init_scrollback() {
+    for console in CONSOLES.iter() {
+        let scrollback = Scrollback::new(SCROLLBACK_LINES);
+        without_interrupts(|| console.lock().scrollback = Some(scrollback));
+    }
 }
 
 impl core::fmt::Write for Screen {
@@ -62,17 +221,10 @@ impl core::fmt::Write for Screen {
 
 impl Screen {
 
-    pub fn delete_last_symbol(&mut self, min_index: u32)
-    {
-        if self.col > min_index
-        {
-            self.col -= 1;
-        }
-        self.write_char_byte(self.line * BUF_WIDTH + self.col, b' ');   
-        self.move_cursor();
-    }
-
     pub fn set_cursor_position(&mut self, position: u16) {
+        if !self.active {
+            return;
+        }
         unsafe {
             let mut cmd_port: PortGeneric<u16, ReadWriteAccess> = Port::new(0x3D4);
             let mut data_port: PortGeneric<u16, ReadWriteAccess> = Port::new(0x3D5);
@@ -85,35 +237,52 @@ impl Screen {
     }
     
     pub fn move_cursor(&mut self){
-        self.set_cursor_position((self.line * BUF_WIDTH + self.col) as u16);
+        self.set_cursor_position(self.text_offset(self.line, self.col) as u16);
     }
 
-    pub fn push_row_to_right(&mut self, row_start: u32)
-    {
-        let mut column = BUF_WIDTH-2;
-        while column != row_start 
-        {
-            let read_char = self.read_char(self.line * BUF_WIDTH + column);
+    /// Returns the (line, column) the next character is printed at, relative to the text area.
+    pub fn cursor_position(&self) -> (u32, u32) {
+        (self.line, self.col)
+    }
 
-            self.write_char(self.line * BUF_WIDTH + column+ 1, read_char);
-            
-            column -= 1;
-        }
+    pub fn set_cursor(&mut self, line: u32, col: u32) {
+        self.line = line.min(self.height() - 1);
+        self.col = col.min(self.width() - 1);
+        self.move_cursor();
+    }
 
-        let read_char = self.read_char(self.line * BUF_WIDTH + column);
+    pub fn text_area(&self) -> Region {
+        self.text_area
+    }
 
-        self.write_char(self.line * BUF_WIDTH + column+ 1, read_char);
+    /// Limits printing, wrapping, scrolling and erasing to `area`, so other regions of the
+    /// screen are left alone. The status bar row is never part of the text area.
+    pub fn set_text_area(&mut self, area: Region) {
+        let ((top, left), (height, width)) = (area.origin(), area.size());
+        let top = top.min(TEXT_HEIGHT - 1);
+        self.text_area = Region::new(top, left, height.min(TEXT_HEIGHT - top), width);
+        self.set_cursor(self.line, self.col);
     }
 
-    pub fn move_print_to(&mut self, x: u32)
-    {
-        self.col = x;
+    /// Lines of the text area.
+    pub fn height(&self) -> u32 {
+        self.text_area.size().0
     }
-    
+
+    /// Columns of the text area.
+    pub fn width(&self) -> u32 {
+        self.text_area.size().1
+    }
+
+    fn text_offset(&self, line: u32, col: u32) -> u32 {
+        self.text_area.offset(line, col)
+    }
+
+    /// Clears the text area, the status bar stays.
     pub fn clear(&mut self) {
-        for i in 0..BUF_HEIGHT {
-            for j in 0..BUF_WIDTH {
-                self.write_char_byte(i * BUF_WIDTH + j, 0x00)
+        for i in 0..self.height() {
+            for j in 0..self.width() {
+                self.write_char_byte(self.text_offset(i, j), 0x00)
             }
         }
         self.col = 0;
@@ -121,58 +290,273 @@ impl Screen {
         self.move_cursor();
     }
 
+    pub fn color(&self) -> ColorCode {
+        self.color
+    }
+
+    /// Color of the characters printed from now on.
+    pub fn set_color(&mut self, color: ColorCode) {
+        self.color = color;
+    }
+
+    pub fn set_foreground(&mut self, foreground: Color) {
+        self.color = self.color.with_foreground(foreground);
+    }
+
+    pub fn set_background(&mut self, background: Color) {
+        self.color = self.color.with_background(background);
+    }
+
+    /// Prints `s` like a VT100 terminal would: ANSI escape sequences move the cursor,
+    /// erase and change the color.
     pub fn print(&mut self, s: &str) {
         for byte in s.bytes() {
-            match byte {
-                b'\n' => {
-                    if self.line == BUF_HEIGHT - 1 {
-                        self.scroll_up();
-                    } else {
-                        self.line += 1;
-                    }
-                    self.col = 0;
-                }
-                b => {
-                    self.write_char_byte(self.line * BUF_WIDTH + self.col, b);
-                    self.col += 1;
-                    if self.col == BUF_WIDTH {
-                        self.col = 0;
-                        self.print("\n");
-                    }
-                }
-            }
-            self.move_cursor();
+            self.print_byte(byte);
+        }
+    }
+
+    pub fn print_byte(&mut self, byte: u8) {
+        self.show_live();
+        match self.parser.advance(byte) {
+            Action::Print(byte) => self.put_byte(byte),
+            Action::Escape(byte) => self.escape(byte),
+            Action::Csi(params, final_byte) => self.control_sequence(&params, final_byte),
+            Action::None => {}
         }
+        self.move_cursor();
     }
 
-    pub fn get_buffer(&mut self) -> [u8; (BUF_HEIGHT * BUF_WIDTH) as usize]
-    {
-        let mut buf = [b' '; (BUF_HEIGHT * BUF_WIDTH) as usize];
+    fn escape(&mut self, byte: u8) {
+        match byte {
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            // set a tab stop at the cursor
+            b'H' => self.tab_stops[self.col as usize] = true,
+            // full reset
+            b'c' => {
+                self.color = DEFAULT_COLOR;
+                self.tab_stops = DEFAULT_TAB_STOPS;
+                self.clear();
+            }
+            _ => {}
+        }
+    }
 
-        for i in 0..BUF_HEIGHT {
-            for j in 0..BUF_WIDTH
-            {
-                buf[(i * BUF_WIDTH + j) as usize] = self.read_char(i * BUF_WIDTH + j).char_byte;
+    fn control_sequence(&mut self, params: &Params, final_byte: u8) {
+        // most sequences take a count, where a missing or zero count means 1
+        let count = params.get(0).max(1) as u32;
+        match final_byte {
+            b'A' => self.line = self.line.saturating_sub(count),
+            b'B' => self.line = (self.line + count).min(self.height() - 1),
+            b'C' => self.col = (self.col + count).min(self.width() - 1),
+            b'D' => self.col = self.col.saturating_sub(count),
+            // rows and columns count from 1
+            b'H' | b'f' => {
+                let line = params.get(0).max(1) as u32 - 1;
+                let col = params.get(1).max(1) as u32 - 1;
+                self.line = line.min(self.height() - 1);
+                self.col = col.min(self.width() - 1);
             }
+            b'G' => self.col = (count - 1).min(self.width() - 1),
+            b'J' => self.erase_in_display(params.get(0)),
+            b'K' => self.erase_in_line(params.get(0)),
+            b'g' => match params.get(0) {
+                0 => self.tab_stops[self.col as usize] = false,
+                3 => self.tab_stops = [false; BUF_WIDTH as usize],
+                _ => {}
+            },
+            b'm' => self.select_graphic_rendition(params),
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            // other control sequences are not supported
+            _ => {}
         }
+    }
 
-        return buf;
+    fn save_cursor(&mut self) {
+        self.saved = (self.line, self.col, self.color);
+    }
+
+    fn restore_cursor(&mut self) {
+        (self.line, self.col, self.color) = self.saved;
+    }
+
+    /// Blanks the cells from `from` up to, not including, `to` with the current background.
+    /// Cells are counted row by row through the text area.
+    fn erase(&mut self, from: u32, to: u32) {
+        let color_byte = self.color.as_u8();
+        let width = self.width();
+        for cell in from..to {
+            let offset = self.text_offset(cell / width, cell % width);
+            self.write_char(offset, AsciiChar { char_byte: b' ', color_byte });
+        }
+    }
+
+    /// 0 erases from the cursor to the end of the text area, 1 from the start to the cursor, 2 everything.
+    fn erase_in_display(&mut self, mode: u16) {
+        let cursor = self.line * self.width() + self.col;
+        let end = self.height() * self.width();
+        match mode {
+            0 => self.erase(cursor, end),
+            1 => self.erase(0, cursor + 1),
+            2 => self.erase(0, end),
+            _ => {}
+        }
+    }
+
+    /// Like `erase_in_display`, limited to the cursor line.
+    fn erase_in_line(&mut self, mode: u16) {
+        let start = self.line * self.width();
+        let cursor = start + self.col;
+        match mode {
+            0 => self.erase(cursor, start + self.width()),
+            1 => self.erase(start, cursor + 1),
+            2 => self.erase(start, start + self.width()),
+            _ => {}
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, params: &Params) {
+        if params.is_empty() {
+            self.color = DEFAULT_COLOR;
+        }
+        for param in params.iter() {
+            self.color = match param {
+                0 => DEFAULT_COLOR,
+                1 => self.color.with_bright_foreground(true),
+                22 => self.color.with_bright_foreground(false),
+                30..=37 => self.color.with_foreground(ANSI_COLORS[param as usize - 30]),
+                39 => self.color.with_foreground(DEFAULT_FOREGROUND),
+                40..=47 => self.color.with_background(ANSI_COLORS[param as usize - 40]),
+                49 => self.color.with_background(DEFAULT_BACKGROUND),
+                90..=97 => self.color.with_foreground(ANSI_BRIGHT_COLORS[param as usize - 90]),
+                // the high background bit makes the text blink, so bright backgrounds use the normal colors
+                100..=107 => self.color.with_background(ANSI_COLORS[param as usize - 100]),
+                _ => self.color,
+            };
+        }
+    }
+
+    fn put_byte(&mut self, byte: u8) {
+        match byte {
+            b'\n' => {
+                if self.line == self.height() - 1 {
+                    self.scroll_up();
+                } else {
+                    self.line += 1;
+                }
+                self.col = 0;
+            }
+            b'\r' => self.col = 0,
+            b'\t' => {
+                let width = self.width() as usize;
+                let next_stop = (self.col as usize + 1..width).find(|&col| self.tab_stops[col]);
+                self.col = next_stop.unwrap_or(width - 1) as u32;
+            }
+            // backspace only moves the cursor, the character stays until it is overwritten
+            0x08 => self.col = self.col.saturating_sub(1),
+            // other control characters are not shown
+            0x00..=0x1f | 0x7f => {}
+            b => {
+                self.write_char_byte(self.text_offset(self.line, self.col), b);
+                self.col += 1;
+                if self.col == self.width() {
+                    self.put_byte(b'\n');
+                }
+            }
+        }
     }
 
+    /// Moves the text area up by one line, the rest of the screen stays.
     fn scroll_up(&mut self) {
+        // the scrollback keeps whole screen rows, so only a text area that spans them adds to it
+        if self.text_area.origin() == (0, 0) && self.width() == BUF_WIDTH {
+            if let Some(scrollback) = self.scrollback.as_mut() {
+                scrollback.push(&self.buffer[..ROW_BYTES]);
+            }
+        }
         for i in 0..self.line {
-            for j in 0..BUF_WIDTH {
-                let char_to_copy = self.read_char(BUF_WIDTH * (i + 1) + j);
-                self.write_char(BUF_WIDTH * i + j, char_to_copy);
+            for j in 0..self.width() {
+                let char_to_copy = self.read_char(self.text_offset(i + 1, j));
+                self.write_char(self.text_offset(i, j), char_to_copy);
+            }
+        }
+        for j in 0..self.width() {
+            self.write_char(self.text_offset(self.line, j), AsciiChar { char_byte: b' ', color_byte: 0x00 });
+        }
+    }
+
+    /// Replaces the status bar with `text`, padded with spaces.
+    pub fn write_status(&mut self, text: &[u8]) {
+        let mut status = Region::new(STATUS_ROW, 0, 1, BUF_WIDTH);
+        status.set_color(STATUS_COLOR);
+        status.write_line(self, 0, text);
+    }
+
+    /// Shows older lines, `lines` further back than the current view.
+    pub fn scroll_view_up(&mut self, lines: usize) {
+        let history = match self.scrollback.as_mut() {
+            Some(scrollback) => scrollback,
+            None => return,
+        };
+        if self.view_offset == 0 {
+            for (row, saved) in history.live.iter_mut().enumerate() {
+                saved.copy_from_slice(&self.buffer[row * ROW_BYTES..(row + 1) * ROW_BYTES]);
             }
         }
-        for i in 0..BUF_WIDTH {
-            self.write_char(self.line * BUF_WIDTH + i, AsciiChar { char_byte: b' ', color_byte: 0x00 });
+        let offset = (self.view_offset + lines).min(history.len());
+        self.show_view(offset);
+    }
+
+    pub fn scroll_view_down(&mut self, lines: usize) {
+        self.show_view(self.view_offset.saturating_sub(lines));
+    }
+
+    /// Returns to the live screen if older lines are shown.
+    pub fn show_live(&mut self) {
+        if self.view_offset != 0 {
+            self.show_view(0);
+        }
+    }
+
+    pub fn is_scrolled_back(&self) -> bool {
+        self.view_offset != 0
+    }
+
+    /// Draws the text rows as they were `offset` lines ago.
+    fn show_view(&mut self, offset: usize) {
+        let history = match self.scrollback.as_ref() {
+            Some(scrollback) if offset != self.view_offset => scrollback,
+            _ => return,
+        };
+        // the lines before the live screen followed by the live screen, shown from `len - offset`
+        let first = history.len() - offset;
+        for row in 0..TEXT_HEIGHT as usize {
+            let line = first + row;
+            let source = match line.checked_sub(history.len()) {
+                Some(live_row) => &history.live[live_row],
+                None => history.row(line),
+            };
+            self.buffer[row * ROW_BYTES..(row + 1) * ROW_BYTES].copy_from_slice(source);
         }
+        self.view_offset = offset;
+
+        self.show_cursor();
     }
 
-    fn write_char_byte(&mut self, offset: u32, char_byte: u8) {
-        self.write_char(offset, AsciiChar { char_byte, color_byte: self.color })
+    /// Puts the hardware cursor where the next character goes, hidden while scrolled back.
+    fn show_cursor(&mut self) {
+        if self.view_offset == 0 {
+            self.move_cursor();
+        } else {
+            // a position past the screen hides the cursor
+            self.set_cursor_position((BUF_HEIGHT * BUF_WIDTH) as u16);
+        }
+    }
+
+    pub fn write_char_byte(&mut self, offset: u32, char_byte: u8) {
+        self.show_live();
+        self.write_char(offset, AsciiChar { char_byte, color_byte: self.color.as_u8() })
     }
 
     fn write_char(&mut self, offset: u32, char: AsciiChar) {
@@ -189,3 +573,273 @@ impl Screen {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn print_writes_at_cursor() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("abc");
+
+            assert_eq!(screen.read_char(0).char_byte, b'a');
+            assert_eq!(screen.read_char(2).char_byte, b'c');
+            assert_eq!((screen.line, screen.col), (0, 3));
+        });
+    }
+
+    #[test_case]
+    fn print_newline_starts_next_line() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("a\nb");
+
+            assert_eq!(screen.read_char(BUF_WIDTH).char_byte, b'b');
+            assert_eq!((screen.line, screen.col), (1, 1));
+        });
+    }
+
+    #[test_case]
+    fn print_wraps_long_lines() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            for _ in 0..BUF_WIDTH {
+                screen.print("a");
+            }
+            screen.print("b");
+
+            assert_eq!(screen.read_char(BUF_WIDTH - 1).char_byte, b'a');
+            assert_eq!(screen.read_char(BUF_WIDTH).char_byte, b'b');
+            assert_eq!((screen.line, screen.col), (1, 1));
+        });
+    }
+
+    #[test_case]
+    fn print_scrolls_at_bottom() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("top\nsecond");
+            for _ in 0..TEXT_HEIGHT - 1 {
+                screen.print("\n");
+            }
+
+            assert_eq!(screen.read_char(0).char_byte, b's');
+            assert_eq!(screen.read_char((TEXT_HEIGHT - 1) * BUF_WIDTH).char_byte, b' ');
+            assert_eq!((screen.line, screen.col), (TEXT_HEIGHT - 1, 0));
+        });
+    }
+
+    #[test_case]
+    fn text_does_not_scroll_into_status_bar() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.write_status(b"status");
+            screen.clear();
+            for _ in 0..BUF_HEIGHT {
+                screen.print("line\n");
+            }
+
+            let status = STATUS_ROW * BUF_WIDTH;
+            assert_eq!(screen.read_char(status).char_byte, b's');
+            assert_eq!(screen.read_char(status).color_byte, STATUS_COLOR.as_u8());
+            assert_eq!(screen.read_char(status + 6).char_byte, b' ');
+            assert_eq!(screen.line, TEXT_HEIGHT - 1);
+        });
+    }
+
+    #[test_case]
+    fn sgr_sequences_set_the_color() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("\x1b[31ma\x1b[1;44mb\x1b[0mc");
+
+            assert_eq!(screen.read_char(0).char_byte, b'a');
+            assert_eq!(screen.read_char(0).color_byte, ColorCode::new(Color::Red, Color::Black).as_u8());
+            assert_eq!(screen.read_char(1).color_byte, ColorCode::new(Color::LightRed, Color::Blue).as_u8());
+            assert_eq!(screen.read_char(2).color_byte, DEFAULT_COLOR.as_u8());
+            assert_eq!((screen.line, screen.col), (0, 3));
+        });
+    }
+
+    #[test_case]
+    fn color_setters() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.set_foreground(Color::Yellow);
+            screen.set_background(Color::Blue);
+            assert_eq!(screen.color(), ColorCode::new(Color::Yellow, Color::Blue));
+            assert_eq!(screen.color().as_u8(), 0x1e);
+            screen.set_color(DEFAULT_COLOR);
+        });
+    }
+
+    #[test_case]
+    fn control_characters_move_the_cursor() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("abc\rx\ty\x08z");
+
+            assert_eq!(screen.read_char(0).char_byte, b'x');
+            assert_eq!(screen.read_char(1).char_byte, b'b');
+            assert_eq!(screen.read_char(TAB_WIDTH as u32).char_byte, b'z');
+            assert_eq!((screen.line, screen.col), (0, TAB_WIDTH as u32 + 1));
+        });
+    }
+
+    #[test_case]
+    fn cursor_movement_sequences() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("\x1b[3;5H");
+            assert_eq!((screen.line, screen.col), (2, 4));
+            screen.print("\x1b[2A\x1b[C\x1b[3C\x1b[B");
+            assert_eq!((screen.line, screen.col), (1, 8));
+            screen.print("\x1b[10D\x1b[H");
+            assert_eq!((screen.line, screen.col), (0, 0));
+            screen.print("\x1b[999;999H");
+            assert_eq!((screen.line, screen.col), (TEXT_HEIGHT - 1, BUF_WIDTH - 1));
+        });
+    }
+
+    #[test_case]
+    fn erase_sequences() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("abcdef\nghi\x1b[1;3H\x1b[K");
+            assert_eq!(screen.read_char(1).char_byte, b'b');
+            assert_eq!(screen.read_char(2).char_byte, b' ');
+            assert_eq!(screen.read_char(BUF_WIDTH).char_byte, b'g');
+
+            screen.print("\x1b[1K");
+            assert_eq!(screen.read_char(0).char_byte, b' ');
+            screen.print("\x1b[J");
+            assert_eq!(screen.read_char(BUF_WIDTH).char_byte, b' ');
+            assert_eq!((screen.line, screen.col), (0, 2));
+        });
+    }
+
+    #[test_case]
+    fn save_and_restore_cursor() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("ab\x1b7\x1b[31m\ncd\x1b8e");
+            assert_eq!(screen.read_char(2).char_byte, b'e');
+            assert_eq!(screen.read_char(2).color_byte, DEFAULT_COLOR.as_u8());
+
+            screen.print("\x1b[2;2H\x1b[s\x1b[H\x1b[u");
+            assert_eq!((screen.line, screen.col), (1, 1));
+        });
+    }
+
+    #[test_case]
+    fn tab_stops_can_be_changed() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("\x1b[3g\x1b[4G\x1bH\r\t");
+            assert_eq!(screen.col, 3);
+            screen.print("\t");
+            assert_eq!(screen.col, BUF_WIDTH - 1);
+            screen.print("\x1bc");
+            screen.print("\t");
+            assert_eq!(screen.col, TAB_WIDTH as u32);
+        });
+    }
+
+    #[test_case]
+    fn scrolled_off_lines_can_be_viewed() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            if screen.scrollback.is_none() {
+                screen.scrollback = Some(Scrollback::new(SCROLLBACK_LINES));
+            }
+            screen.clear();
+            screen.print("first\n");
+            for _ in 0..TEXT_HEIGHT {
+                screen.print("next\n");
+            }
+            assert_eq!(screen.read_char(0).char_byte, b'n');
+
+            screen.scroll_view_up(2);
+            assert!(screen.is_scrolled_back());
+            assert_eq!(screen.read_char(0).char_byte, b'f');
+            assert_eq!(screen.read_char(BUF_WIDTH).char_byte, b'n');
+
+            screen.scroll_view_down(1);
+            assert_eq!(screen.read_char(0).char_byte, b'n');
+
+            // printing returns to the live screen first
+            screen.print("x");
+            assert!(!screen.is_scrolled_back());
+            assert_eq!(screen.read_char((TEXT_HEIGHT - 1) * BUF_WIDTH).char_byte, b'x');
+        });
+    }
+
+    #[test_case]
+    fn text_area_scrolls_without_touching_a_pane_beside_it() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.set_text_area(Region::new(0, 0, TEXT_HEIGHT, 60));
+            screen.clear();
+            let mut log = Region::new(0, 60, TEXT_HEIGHT, BUF_WIDTH - 60);
+            log.clear(&mut screen);
+            log.print(&mut screen, "log");
+
+            let line = "shell output that is long enough to wrap at the edge of the text area";
+            screen.print(line);
+            assert_eq!(screen.cursor_position(), (1, line.len() as u32 - 60));
+            for _ in 0..TEXT_HEIGHT {
+                screen.print("\n");
+                screen.print(line);
+            }
+            screen.print("\n\x1b[2J");
+
+            assert_eq!(screen.read_char(60).char_byte, b'l');
+            assert_eq!(screen.read_char(62).char_byte, b'g');
+            assert_eq!(screen.read_char(59).char_byte, b' ');
+            assert_eq!(screen.cursor_position().1, 0);
+
+            screen.set_text_area(Region::new(0, 0, TEXT_HEIGHT, BUF_WIDTH));
+            screen.clear();
+        });
+    }
+
+    #[test_case]
+    fn switching_consoles_keeps_their_text() {
+        without_interrupts(|| {
+            CONSOLES[0].lock().clear();
+            CONSOLES[0].lock().print("first");
+            CONSOLES[1].lock().clear();
+            CONSOLES[1].lock().print("second");
+        });
+
+        switch_console(1);
+        crate::print!("!");
+        without_interrupts(|| {
+            let shown = CONSOLES[1].lock();
+            assert!(shown.active);
+            assert_eq!(shown.buffer.as_ptr() as usize, 0xb8000);
+            assert_eq!(shown.read_char(6).char_byte, b'!');
+            assert_eq!(CONSOLES[0].lock().read_char(0).char_byte, b'f');
+        });
+
+        switch_console(0);
+        without_interrupts(|| {
+            let shown = CONSOLES[0].lock();
+            assert_eq!(shown.buffer.as_ptr() as usize, 0xb8000);
+            assert_eq!(shown.read_char(0).char_byte, b'f');
+            assert_eq!(CONSOLES[1].lock().read_char(0).char_byte, b's');
+        });
+    }
+}
@@ -0,0 +1,190 @@
+/// Parameters beyond this count are ignored.
+const MAX_PARAMS: usize = 8;
+
+/// Numeric parameters of a control sequence, a missing parameter is 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    values: [u16; MAX_PARAMS],
+    len: usize,
+    // set after the last parameter that is kept, the digits that follow are dropped
+    overflow: bool,
+}
+
+impl Params {
+    const fn new() -> Params {
+        Params { values: [0; MAX_PARAMS], len: 0, overflow: false }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The parameter at `index`, 0 if it is missing.
+    pub fn get(&self, index: usize) -> u16 {
+        self.values[..self.len].get(index).copied().unwrap_or(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u16> + '_ {
+        self.values[..self.len].iter().copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// A byte to show.
+    Print(u8),
+    /// A two byte escape sequence `ESC byte`, e.g. `ESC 7`.
+    Escape(u8),
+    /// A complete control sequence `ESC [ params final_byte`.
+    Csi(Params, u8),
+    /// The byte was part of an escape sequence.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    // after the intermediate bytes of e.g. `ESC ( B`, waiting for the final byte
+    EscapeIntermediate,
+    Csi,
+}
+
+/// Splits output into printable bytes and ANSI escape sequences, one byte at a time.
+pub struct Parser {
+    state: State,
+    params: Params,
+}
+
+impl Parser {
+    pub const fn new() -> Parser {
+        Parser { state: State::Ground, params: Params::new() }
+    }
+
+    pub fn advance(&mut self, byte: u8) -> Action {
+        match (self.state, byte) {
+            (State::Ground, 0x1b) => {
+                self.state = State::Escape;
+                Action::None
+            }
+            (State::Ground, byte) => Action::Print(byte),
+            (State::Escape, b'[') => {
+                self.state = State::Csi;
+                self.params = Params::new();
+                Action::None
+            }
+            (State::Escape, 0x20..=0x2f) => {
+                self.state = State::EscapeIntermediate;
+                Action::None
+            }
+            (State::Escape, byte) => {
+                self.state = State::Ground;
+                Action::Escape(byte)
+            }
+            // e.g. character set selection, which is not supported
+            (State::EscapeIntermediate, 0x20..=0x2f) => Action::None,
+            (State::EscapeIntermediate, _) => {
+                self.state = State::Ground;
+                Action::None
+            }
+            (State::Csi, b'0'..=b'9') if self.params.overflow => Action::None,
+            (State::Csi, b'0'..=b'9') => {
+                if self.params.len == 0 {
+                    self.params.len = 1;
+                }
+                if let Some(value) = self.params.values.get_mut(self.params.len - 1) {
+                    *value = value.saturating_mul(10).saturating_add((byte - b'0') as u16);
+                }
+                Action::None
+            }
+            (State::Csi, b';') => {
+                // `ESC [ ;5 m` has an empty first parameter
+                let len = self.params.len.max(1);
+                if len == MAX_PARAMS {
+                    self.params.overflow = true;
+                } else {
+                    self.params.len = len + 1;
+                }
+                Action::None
+            }
+            (State::Csi, 0x40..=0x7e) => {
+                self.state = State::Ground;
+                Action::Csi(self.params, byte)
+            }
+            // private markers like `?` and intermediate bytes are ignored
+            (State::Csi, 0x20..=0x3f) => Action::None,
+            // anything else cancels the sequence
+            (State::Csi, _) => {
+                self.state = State::Ground;
+                Action::None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn parse(bytes: &[u8]) -> Vec<Action> {
+        let mut parser = Parser::new();
+        bytes
+            .iter()
+            .map(|&byte| parser.advance(byte))
+            .filter(|action| *action != Action::None)
+            .collect()
+    }
+
+    fn params(actions: &[Action]) -> Vec<u16> {
+        match actions {
+            [Action::Csi(params, _)] => params.iter().collect(),
+            _ => panic!("expected one control sequence, got {:?}", actions),
+        }
+    }
+
+    #[test_case]
+    fn plain_text_is_printed() {
+        assert_eq!(parse(b"a\n"), [Action::Print(b'a'), Action::Print(b'\n')]);
+    }
+
+    #[test_case]
+    fn parses_control_sequence_parameters() {
+        let actions = parse(b"\x1b[1;31m");
+        assert!(matches!(actions[..], [Action::Csi(_, b'm')]));
+        assert_eq!(params(&actions), [1, 31]);
+
+        assert_eq!(params(&parse(b"\x1b[m")), []);
+        assert_eq!(params(&parse(b"\x1b[;5H")), [0, 5]);
+    }
+
+    #[test_case]
+    fn parameters_beyond_the_limit_are_ignored() {
+        assert_eq!(params(&parse(b"\x1b[0;0;0;0;0;0;0;3;1m")), [0, 0, 0, 0, 0, 0, 0, 3]);
+        assert_eq!(params(&parse(b"\x1b[1;2;3;4;5;6;7;8;9;10H")), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test_case]
+    fn parses_escape_sequences() {
+        assert_eq!(parse(b"\x1b7x"), [Action::Escape(b'7'), Action::Print(b'x')]);
+        assert_eq!(parse(b"\x1b[3\nx"), [Action::Print(b'x')]);
+    }
+
+    #[test_case]
+    fn escape_intermediate_bytes_are_not_printed() {
+        assert_eq!(parse(b"\x1b(Bx"), [Action::Print(b'x')]);
+        assert_eq!(parse(b"\x1b#8\x1b7"), [Action::Escape(b'7')]);
+    }
+
+    #[test_case]
+    fn missing_parameters_are_zero() {
+        match parse(b"\x1b[;5H")[..] {
+            [Action::Csi(params, b'H')] => {
+                assert_eq!(params.get(0), 0);
+                assert_eq!(params.get(1), 5);
+                assert_eq!(params.get(2), 0);
+            }
+            _ => panic!("expected a control sequence"),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use super::{AsciiChar, ColorCode, Screen, BUF_HEIGHT, BUF_WIDTH, DEFAULT_COLOR, TAB_WIDTH, TEXT_HEIGHT};
+
+/// A rectangle of a screen with its own cursor, color and scrolling.
+/// Output is clipped to the rectangle, so e.g. a log pane next to the shell never draws over it.
+/// Only `\n`, `\r`, `\t` and backspace are interpreted, escape sequences are not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    top: u32,
+    left: u32,
+    height: u32,
+    width: u32,
+    // cursor, relative to the top left corner
+    line: u32,
+    col: u32,
+    color: ColorCode,
+}
+
+impl Region {
+    /// The rectangle is shrunk to fit on the screen, it keeps at least one cell.
+    pub fn new(top: u32, left: u32, height: u32, width: u32) -> Region {
+        let top = top.min(BUF_HEIGHT - 1);
+        let left = left.min(BUF_WIDTH - 1);
+        Region {
+            top,
+            left,
+            height: height.clamp(1, BUF_HEIGHT - top),
+            width: width.clamp(1, BUF_WIDTH - left),
+            line: 0,
+            col: 0,
+            color: DEFAULT_COLOR,
+        }
+    }
+
+    /// Returns the (line, column) of the top left corner on the screen.
+    pub fn origin(&self) -> (u32, u32) {
+        (self.top, self.left)
+    }
+
+    /// Returns the (height, width) in characters.
+    pub fn size(&self) -> (u32, u32) {
+        (self.height, self.width)
+    }
+
+    /// Returns the (line, column) the next character is printed at, relative to the origin.
+    pub fn cursor_position(&self) -> (u32, u32) {
+        (self.line, self.col)
+    }
+
+    pub fn set_cursor(&mut self, line: u32, col: u32) {
+        self.line = line.min(self.height - 1);
+        self.col = col.min(self.width - 1);
+    }
+
+    pub fn color(&self) -> ColorCode {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: ColorCode) {
+        self.color = color;
+    }
+
+    /// Moves the hardware cursor to the region's cursor.
+    pub fn move_cursor(&self, screen: &mut Screen) {
+        screen.set_cursor_position(self.offset(self.line, self.col) as u16);
+    }
+
+    /// Blanks the region with its color and moves the cursor to the top left corner.
+    pub fn clear(&mut self, screen: &mut Screen) {
+        self.show_live(screen);
+        for line in 0..self.height {
+            self.erase_line(screen, line);
+        }
+        self.line = 0;
+        self.col = 0;
+    }
+
+    pub fn print(&mut self, screen: &mut Screen, s: &str) {
+        self.show_live(screen);
+        for byte in s.bytes() {
+            self.put_byte(screen, byte);
+        }
+    }
+
+    /// Replaces `line` with `text`, cut at the right edge and padded with spaces.
+    /// The cursor does not move.
+    pub fn write_line(&self, screen: &mut Screen, line: u32, text: &[u8]) {
+        if line >= self.height {
+            return;
+        }
+        self.show_live(screen);
+        for col in 0..self.width {
+            let char_byte = text.get(col as usize).copied().unwrap_or(b' ');
+            self.write(screen, line, col, char_byte);
+        }
+    }
+
+    fn put_byte(&mut self, screen: &mut Screen, byte: u8) {
+        match byte {
+            b'\n' => {
+                if self.line == self.height - 1 {
+                    self.scroll_up(screen);
+                } else {
+                    self.line += 1;
+                }
+                self.col = 0;
+            }
+            b'\r' => self.col = 0,
+            // tab stops every `TAB_WIDTH` columns of the region
+            b'\t' => self.col = ((self.col / TAB_WIDTH as u32 + 1) * TAB_WIDTH as u32).min(self.width - 1),
+            0x08 => self.col = self.col.saturating_sub(1),
+            0x00..=0x1f | 0x7f => {}
+            b => {
+                self.write(screen, self.line, self.col, b);
+                self.col += 1;
+                if self.col == self.width {
+                    self.put_byte(screen, b'\n');
+                }
+            }
+        }
+    }
+
+    /// Moves the lines of the region up by one, the rest of the screen stays.
+    fn scroll_up(&mut self, screen: &mut Screen) {
+        for line in 1..self.height {
+            for col in 0..self.width {
+                let char = screen.read_char(self.offset(line, col));
+                screen.write_char(self.offset(line - 1, col), char);
+            }
+        }
+        self.erase_line(screen, self.height - 1);
+    }
+
+    fn erase_line(&self, screen: &mut Screen, line: u32) {
+        for col in 0..self.width {
+            self.write(screen, line, col, b' ');
+        }
+    }
+
+    fn write(&self, screen: &mut Screen, line: u32, col: u32, char_byte: u8) {
+        screen.write_char(self.offset(line, col), AsciiChar { char_byte, color_byte: self.color.as_u8() });
+    }
+
+    /// Position in the screen buffer of a cell given relative to the origin.
+    pub(super) fn offset(&self, line: u32, col: u32) -> u32 {
+        (self.top + line) * BUF_WIDTH + self.left + col
+    }
+
+    /// Text rows can not be drawn over while older lines are shown, the status bar can.
+    fn show_live(&self, screen: &mut Screen) {
+        if self.top < TEXT_HEIGHT {
+            screen.show_live();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Color, CONSOLES};
+    use x86_64::instructions::interrupts::without_interrupts;
+
+    #[test_case]
+    fn new_region_fits_on_the_screen() {
+        let region = Region::new(20, 70, 10, 20);
+        assert_eq!(region.origin(), (20, 70));
+        assert_eq!(region.size(), (BUF_HEIGHT - 20, BUF_WIDTH - 70));
+
+        let region = Region::new(BUF_HEIGHT, BUF_WIDTH, 0, 0);
+        assert_eq!(region.origin(), (BUF_HEIGHT - 1, BUF_WIDTH - 1));
+        assert_eq!(region.size(), (1, 1));
+    }
+
+    #[test_case]
+    fn print_wraps_and_scrolls_inside_the_region() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            screen.print("outside");
+            let mut pane = Region::new(0, 2, 2, 3);
+
+            pane.print(&mut screen, "abcdefg");
+            // "abc" scrolled away, the columns left of the pane are untouched
+            assert_eq!(screen.read_char(0).char_byte, b'o');
+            assert_eq!(screen.read_char(2).char_byte, b'd');
+            assert_eq!(screen.read_char(BUF_WIDTH + 2).char_byte, b'g');
+            assert_eq!(screen.read_char(6).char_byte, b'e');
+            assert_eq!(pane.cursor_position(), (1, 1));
+        });
+    }
+
+    #[test_case]
+    fn write_line_is_clipped_and_padded() {
+        without_interrupts(|| {
+            let mut screen = CONSOLES[0].lock();
+            screen.clear();
+            let mut pane = Region::new(1, 4, 1, 3);
+            pane.set_color(ColorCode::new(Color::Red, Color::Black));
+
+            pane.write_line(&mut screen, 0, b"abcdef");
+            assert_eq!(screen.read_char(BUF_WIDTH + 6).char_byte, b'c');
+            assert_eq!(screen.read_char(BUF_WIDTH + 7).char_byte, 0x00);
+
+            pane.write_line(&mut screen, 0, b"x");
+            assert_eq!(screen.read_char(BUF_WIDTH + 5).char_byte, b' ');
+            assert_eq!(screen.read_char(BUF_WIDTH + 4).color_byte, pane.color().as_u8());
+        });
+    }
+}
@@ -0,0 +1,87 @@
+use super::{BUF_WIDTH, TEXT_HEIGHT};
+use alloc::boxed::Box;
+use alloc::vec;
+
+/// Lines kept after they scrolled off the top of the screen.
+pub const SCROLLBACK_LINES: usize = 500;
+/// A screen row as stored in the VGA buffer, character and attribute bytes.
+pub const ROW_BYTES: usize = BUF_WIDTH as usize * 2;
+
+pub type Row = [u8; ROW_BYTES];
+
+/// Ring of the rows that scrolled off the screen, allocated once so printing never allocates.
+pub struct Scrollback {
+    rows: Box<[Row]>,
+    // index of the oldest row
+    start: usize,
+    len: usize,
+    /// Copy of the text rows while an older part is shown.
+    pub live: Box<[Row]>,
+}
+
+impl Scrollback {
+    pub fn new(capacity: usize) -> Scrollback {
+        Scrollback {
+            rows: vec![[0; ROW_BYTES]; capacity].into_boxed_slice(),
+            start: 0,
+            len: 0,
+            live: vec![[0; ROW_BYTES]; TEXT_HEIGHT as usize].into_boxed_slice(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Appends a row, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, row: &[u8]) {
+        let capacity = self.rows.len();
+        if capacity == 0 {
+            return;
+        }
+        let index = (self.start + self.len) % capacity;
+        self.rows[index].copy_from_slice(row);
+        if self.len == capacity {
+            self.start = (self.start + 1) % capacity;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// The row at `index`, 0 is the oldest.
+    pub fn row(&self, index: usize) -> &Row {
+        &self.rows[(self.start + index) % self.rows.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(byte: u8) -> Row {
+        [byte; ROW_BYTES]
+    }
+
+    #[test_case]
+    fn keeps_rows_in_order() {
+        let mut scrollback = Scrollback::new(3);
+        scrollback.push(&row(1));
+        scrollback.push(&row(2));
+
+        assert_eq!(scrollback.len(), 2);
+        assert_eq!(scrollback.row(0)[0], 1);
+        assert_eq!(scrollback.row(1)[0], 2);
+    }
+
+    #[test_case]
+    fn drops_oldest_rows_when_full() {
+        let mut scrollback = Scrollback::new(3);
+        for byte in 1..=5 {
+            scrollback.push(&row(byte));
+        }
+
+        assert_eq!(scrollback.len(), 3);
+        assert_eq!(scrollback.row(0)[0], 3);
+        assert_eq!(scrollback.row(2)[0], 5);
+    }
+}